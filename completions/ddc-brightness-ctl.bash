@@ -0,0 +1,86 @@
+# bash completion for ddc-brightness-ctl
+
+_ddc_brightness_ctl_displays() {
+    # display numbers from the (usually cached) display list
+    ddc-brightness-ctl --list 2>/dev/null | sed -n 's/^  - \[\([0-9]*\)\].*/\1/p'
+}
+
+_ddc_brightness_ctl() {
+    local cur prev words cword split
+    _init_completion -s || return
+
+    local opts="-d --display --exclude --exclude-model --require-feature -l --list --list-buses
+        --bus --interactive --brightness --sort --date-format --filter --only-brightness
+        --show-features --get --format --set --inc --dec --step --perceptual --inc-pct --dec-pct
+        --toggle --equalize --sync-from --relative-to --offset --contrast-set --contrast-inc
+        --contrast-dec --volume-set --volume-inc --volume-dec --red --green --blue --input
+        --identify --capabilities --features --color-temp --preset --power --vcp-get --vcp-set
+        --reset -y --yes --confirm --save-profile --load-profile --set-relative-to --undo --auto
+        --location --sunrise --sunset --watch --interval --json --raw --fade --retries --delay
+        --timeout --no-cache --refresh-cache --no-dedupe --notify --wake-first --off-after
+        --fail-fast --max-concurrency --no-wait --script --dry-run --verify --timing --min --max
+        --force --exit-code --daemon --color --config --no-config -q --quiet -v --verbose -V
+        --version -h --help"
+
+    case $prev in
+        --config | --script)
+            _filedir
+            return
+            ;;
+        -d | --display | --exclude | --sync-from | --relative-to)
+            COMPREPLY=($(compgen -W "$(_ddc_brightness_ctl_displays)" -- "$cur"))
+            return
+            ;;
+        --input)
+            COMPREPLY=($(compgen -W "vga1 vga2 dvi1 dvi2 composite1 composite2 svideo1 svideo2
+                tuner1 tuner2 tuner3 component1 component2 component3 dp1 dp2 hdmi1 hdmi2
+                usbc" -- "$cur"))
+            return
+            ;;
+        --color-temp)
+            COMPREPLY=($(compgen -W "srgb native 4000k 5000k 6500k 7500k 8200k 9300k 10000k
+                11500k user1 user2 user3" -- "$cur"))
+            return
+            ;;
+        --power)
+            COMPREPLY=($(compgen -W "on standby suspend off" -- "$cur"))
+            return
+            ;;
+        --color)
+            COMPREPLY=($(compgen -W "auto always never" -- "$cur"))
+            return
+            ;;
+        --bus)
+            COMPREPLY=($(compgen -W "$(ls -d /dev/i2c-* 2>/dev/null)" -- "$cur"))
+            return
+            ;;
+        --sort)
+            COMPREPLY=($(compgen -W "index model serial" -- "$cur"))
+            return
+            ;;
+        --date-format)
+            COMPREPLY=($(compgen -W "week date" -- "$cur"))
+            return
+            ;;
+        --format)
+            COMPREPLY=($(compgen -W "text value bar" -- "$cur"))
+            return
+            ;;
+        --exclude-model | --require-feature | --filter | --set | --step | --inc-pct | --dec-pct | --toggle | \
+            --contrast-set | --contrast-inc | --contrast-dec | \
+            --volume-set | --volume-inc | --volume-dec | --red | --green | --blue | \
+            --preset | --vcp-get | --vcp-set | \
+            --save-profile | --load-profile | --set-relative-to | --interval | --fade | \
+            --retries | --delay | --timeout | --offset | --max-concurrency | --off-after | \
+            --min | --max | --confirm | --location | --sunrise | --sunset)
+            return
+            ;;
+    esac
+
+    $split && return
+
+    COMPREPLY=($(compgen -W "$opts" -- "$cur"))
+    [[ ${COMPREPLY-} == *= ]] && compopt -o nospace
+}
+
+complete -F _ddc_brightness_ctl ddc-brightness-ctl
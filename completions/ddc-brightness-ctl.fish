@@ -0,0 +1,98 @@
+# fish completion for ddc-brightness-ctl
+
+function __ddc_brightness_ctl_displays
+    # display numbers and models from the (usually cached) display list
+    ddc-brightness-ctl --list 2>/dev/null | sed -n 's/^  - \[\([0-9]*\)\]: \(.*\) - (.*/\1\t\2/p'
+end
+
+complete -c ddc-brightness-ctl -f
+complete -c ddc-brightness-ctl -s d -l display -x -a '(__ddc_brightness_ctl_displays)' -d 'Select displays by number, serial:SERIAL, connector or model'
+complete -c ddc-brightness-ctl -l exclude -x -a '(__ddc_brightness_ctl_displays)' -d 'Skip a display'
+complete -c ddc-brightness-ctl -l exclude-model -x -d 'Skip displays whose model contains MODEL'
+complete -c ddc-brightness-ctl -l require-feature -x -d 'Only use displays supporting the VCP feature CODE'
+complete -c ddc-brightness-ctl -s l -l list -d 'List all detected displays and metadata'
+complete -c ddc-brightness-ctl -l interactive -d 'Ask which display to use when several are selected'
+complete -c ddc-brightness-ctl -l list-buses -d 'List the i2c buses displays may be on'
+complete -c ddc-brightness-ctl -l bus -x -a '(__fish_complete_path /dev/i2c-)' -d 'Use the display on i2c bus N directly'
+complete -c ddc-brightness-ctl -l brightness -d 'Show the current brightness with --list'
+complete -c ddc-brightness-ctl -l sort -x -a 'index model serial' -d 'Order --list by KEY'
+complete -c ddc-brightness-ctl -l date-format -x -a 'week date' -d 'Show manufacture dates with --list as FORMAT'
+complete -c ddc-brightness-ctl -l filter -x -d 'Only list displays whose model contains MODEL'
+complete -c ddc-brightness-ctl -l only-brightness -d 'Only list displays which support brightness'
+complete -c ddc-brightness-ctl -l show-features -d 'Show which features each display supports with --list'
+complete -c ddc-brightness-ctl -l get -f -a 'brightness contrast input power color-temp red green blue volume' -d 'Get the current brightness, or the listed features'
+complete -c ddc-brightness-ctl -l format -x -a 'text value bar' -d 'Print --get output as FORMAT'
+complete -c ddc-brightness-ctl -l set -x -d 'Set brightness to NUM percent'
+complete -c ddc-brightness-ctl -l inc -d 'Increase brightness by =NUM percent, or the step'
+complete -c ddc-brightness-ctl -l dec -d 'Decrease brightness by =NUM percent, or the step'
+complete -c ddc-brightness-ctl -l perceptual -d 'Make --inc and --dec steps look even'
+complete -c ddc-brightness-ctl -l step -x -d 'How much --inc and --dec change brightness by default'
+complete -c ddc-brightness-ctl -l inc-pct -x -d 'Increase brightness by NUM percent of its current value'
+complete -c ddc-brightness-ctl -l dec-pct -x -d 'Decrease brightness by NUM percent of its current value'
+complete -c ddc-brightness-ctl -l toggle -x -d 'Toggle brightness between LOW,HIGH'
+complete -c ddc-brightness-ctl -l equalize -d 'Set every display to their average brightness, or =NUM'
+complete -c ddc-brightness-ctl -l sync-from -x -a '(__ddc_brightness_ctl_displays)' -d 'Set displays to the brightness of display NUM'
+complete -c ddc-brightness-ctl -l relative-to -x -a '(__ddc_brightness_ctl_displays)' -d 'Set displays relative to the brightness of display NUM'
+complete -c ddc-brightness-ctl -l offset -x -d 'With --relative-to, how far above or below display NUM to set'
+complete -c ddc-brightness-ctl -l contrast-set -x -d 'Set contrast to NUM percent'
+complete -c ddc-brightness-ctl -l contrast-inc -x -d 'Increase contrast by NUM percent'
+complete -c ddc-brightness-ctl -l contrast-dec -x -d 'Decrease contrast by NUM percent'
+complete -c ddc-brightness-ctl -l volume-set -x -d 'Set volume to NUM percent'
+complete -c ddc-brightness-ctl -l volume-inc -x -d 'Increase volume by NUM percent'
+complete -c ddc-brightness-ctl -l volume-dec -x -d 'Decrease volume by NUM percent'
+complete -c ddc-brightness-ctl -l red -x -d 'Set the red video gain to NUM percent'
+complete -c ddc-brightness-ctl -l green -x -d 'Set the green video gain to NUM percent'
+complete -c ddc-brightness-ctl -l blue -x -d 'Set the blue video gain to NUM percent'
+complete -c ddc-brightness-ctl -l input -x -a 'vga1 vga2 dvi1 dvi2 composite1 composite2 svideo1 svideo2 tuner1 tuner2 tuner3 component1 component2 component3 dp1 dp2 hdmi1 hdmi2 usbc' -d 'Switch to an input'
+complete -c ddc-brightness-ctl -l identify -d 'Flash the brightness of the selected displays'
+complete -c ddc-brightness-ctl -l capabilities -d 'Print the capabilities of the selected displays'
+complete -c ddc-brightness-ctl -l features -d 'Print the value of every supported feature of the selected displays'
+complete -c ddc-brightness-ctl -l color-temp -x -a 'srgb native 4000k 5000k 6500k 7500k 8200k 9300k 10000k 11500k user1 user2 user3' -d 'Select a colour preset'
+complete -c ddc-brightness-ctl -l preset -x -d 'Set the values of a preset from the config'
+complete -c ddc-brightness-ctl -l power -x -a 'on standby suspend off' -d 'Switch the power mode'
+complete -c ddc-brightness-ctl -l vcp-get -x -d 'Print the raw value of a VCP feature'
+complete -c ddc-brightness-ctl -l vcp-set -x -d 'Write CODE=VALUE to a VCP feature'
+complete -c ddc-brightness-ctl -l reset -d 'Restore factory defaults'
+complete -c ddc-brightness-ctl -s y -l yes -d 'Reset without asking for confirmation'
+complete -c ddc-brightness-ctl -l confirm -x -d 'Ask before changing brightness by more than NUM percent'
+complete -c ddc-brightness-ctl -l save-profile -x -d 'Save the brightness of each display as a profile'
+complete -c ddc-brightness-ctl -l load-profile -x -d 'Restore the brightness saved in a profile'
+complete -c ddc-brightness-ctl -l set-relative-to -x -d 'Restore a profile with a relative change'
+complete -c ddc-brightness-ctl -l undo -d 'Restore the brightness from before the last change'
+complete -c ddc-brightness-ctl -l auto -d 'Load profile day or night depending on the time'
+complete -c ddc-brightness-ctl -l location -x -d 'Latitude and longitude for --auto'
+complete -c ddc-brightness-ctl -l sunrise -x -d 'Time day starts for --auto, as HH:MM'
+complete -c ddc-brightness-ctl -l sunset -x -d 'Time night starts for --auto, as HH:MM'
+complete -c ddc-brightness-ctl -l watch -d 'Print the brightness whenever it changes'
+complete -c ddc-brightness-ctl -l interval -x -d 'How often --watch polls, in ms'
+complete -c ddc-brightness-ctl -l json -d 'Print output as JSON'
+complete -c ddc-brightness-ctl -l raw -d "Work in the display's own units instead of percentages"
+complete -c ddc-brightness-ctl -l fade -x -d 'Change to the new value gradually over MS, with easing EASE'
+complete -c ddc-brightness-ctl -l retries -x -d 'Retry failed DDC commands N times'
+complete -c ddc-brightness-ctl -l delay -x -d 'Wait MS between DDC commands'
+complete -c ddc-brightness-ctl -l timeout -x -d 'Wait MS for displays to reply to reads'
+complete -c ddc-brightness-ctl -l no-cache -d 'Do not read or write the display cache'
+complete -c ddc-brightness-ctl -l refresh-cache -d 'Enumerate displays again and rebuild the cache'
+complete -c ddc-brightness-ctl -l no-dedupe -d 'Keep displays found more than once'
+complete -c ddc-brightness-ctl -l notify -d 'Show a desktop notification after a change'
+complete -c ddc-brightness-ctl -l wake-first -d 'Switch displays on and wait, or =MS, before acting on them'
+complete -c ddc-brightness-ctl -l off-after -x -d 'Switch displays off SECONDS after acting on them'
+complete -c ddc-brightness-ctl -l fail-fast -d 'Stop at the first display which fails'
+complete -c ddc-brightness-ctl -l max-concurrency -x -d 'Act on at most N displays at once'
+complete -c ddc-brightness-ctl -l no-wait -d 'Fail rather than wait when another invocation is changing displays'
+complete -c ddc-brightness-ctl -l script -r -F -d 'Run each line of FILE as further arguments'
+complete -c ddc-brightness-ctl -l dry-run -d 'Print what would be changed without writing'
+complete -c ddc-brightness-ctl -l verify -d 'Read features back after changing them'
+complete -c ddc-brightness-ctl -l timing -d 'Print how long each read and write took'
+complete -c ddc-brightness-ctl -l min -x -d 'Never set brightness below NUM percent'
+complete -c ddc-brightness-ctl -l max -x -d 'Never set brightness above NUM percent'
+complete -c ddc-brightness-ctl -l force -d 'Let decreases go below the safe floor of a display'
+complete -c ddc-brightness-ctl -l exit-code -d 'Exit with the brightness read by --get'
+complete -c ddc-brightness-ctl -l daemon -d 'Run as a daemon which coalesces relative changes'
+complete -c ddc-brightness-ctl -l color -x -a 'auto always never' -d 'When to colour errors'
+complete -c ddc-brightness-ctl -l config -r -F -d 'Read default options from PATH'
+complete -c ddc-brightness-ctl -l no-config -d "Don't read the config file"
+complete -c ddc-brightness-ctl -s q -l quiet -d 'Only print errors and the output asked for'
+complete -c ddc-brightness-ctl -s v -l verbose -d 'Print diagnostics, twice for every DDC command'
+complete -c ddc-brightness-ctl -s V -l version -d 'Print the program version'
+complete -c ddc-brightness-ctl -s h -l help -d 'Print the help message'
@@ -0,0 +1,122 @@
+use std::{fs, io, path::PathBuf};
+
+use crate::{Backend, Monitor};
+
+// backlights are only looked for on Linux, elsewhere the internal panel is left to DDC/CI
+#[cfg(target_os = "linux")]
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+#[cfg(target_os = "linux")]
+const DRM_DIR: &str = "/sys/class/drm";
+// connector types used for built in panels
+#[cfg(target_os = "linux")]
+const INTERNAL_CONNECTORS: &[&str] = &["eDP", "LVDS", "DSI"];
+// when a panel has several interfaces, firmware ones know about quirks the others don't
+#[cfg(target_os = "linux")]
+const TYPE_PREFERENCE: &[&str] = &["firmware", "platform", "raw"];
+
+/// A backlight exposed by the kernel under `/sys/class/backlight`.
+#[derive(Debug)]
+pub struct Backlight {
+    path: PathBuf,
+}
+
+impl Backlight {
+    /// The name of the backlight device, e.g. `intel_backlight`.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn read_value(&self, file: &str) -> io::Result<u32> {
+        fs::read_to_string(self.path.join(file))?
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{file}: {e}")))
+    }
+
+    // some backlights have maximums beyond u16, so their values are scaled down to fit
+    fn scale(maximum: u32) -> u32 {
+        maximum.div_ceil(u32::from(u16::MAX)).max(1)
+    }
+
+    // reads the value and maximum, in the same form as a VCP feature
+    pub(crate) fn read(&self) -> io::Result<(u16, u16)> {
+        let maximum = self.read_value("max_brightness")?;
+        if maximum == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "backlight reports a maximum brightness of 0",
+            ));
+        }
+        let value = self.read_value("actual_brightness")?.min(maximum);
+
+        let scale = Self::scale(maximum);
+        Ok(((value / scale) as u16, (maximum / scale) as u16))
+    }
+
+    pub(crate) fn write(&self, value: u16) -> io::Result<()> {
+        let maximum = self.read_value("max_brightness")?;
+        let value = (u32::from(value) * Self::scale(maximum)).min(maximum);
+        fs::write(self.path.join("brightness"), value.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn backlight_type(path: &std::path::Path) -> Option<usize> {
+    let ty = fs::read_to_string(path.join("type")).ok()?;
+    TYPE_PREFERENCE.iter().position(|t| *t == ty.trim())
+}
+
+// the EDID of the built in panel, which identifies it the same way as DDC displays
+#[cfg(target_os = "linux")]
+fn internal_edid() -> Option<Vec<u8>> {
+    fs::read_dir(DRM_DIR)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            INTERNAL_CONNECTORS
+                .iter()
+                .any(|connector| name.contains(&format!("-{connector}-")))
+        })
+        .filter_map(|entry| fs::read(entry.path().join("edid")).ok())
+        .find(|edid| !edid.is_empty())
+}
+
+/// Finds the backlight of the built in panel, if there is one.
+#[cfg(target_os = "linux")]
+pub(crate) fn internal_panel() -> Option<Monitor> {
+    use ddc_hi::DisplayInfo;
+
+    let backlight = fs::read_dir(BACKLIGHT_DIR)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| Some((backlight_type(&path)?, path)))
+        .min_by_key(|(preference, _)| *preference)
+        .map(|(_, path)| Backlight { path })?;
+
+    // ddc-hi has no backend for sysfs, so the info claims i2c but `Monitor::backend` is what
+    // decides how the display is controlled
+    let id = format!("backlight:{}", backlight.name());
+    let info = internal_edid()
+        .and_then(|edid| DisplayInfo::from_edid(ddc_hi::Backend::I2cDevice, id.clone(), edid).ok())
+        .unwrap_or_else(|| {
+            let mut info = DisplayInfo::new(ddc_hi::Backend::I2cDevice, id);
+            info.model_name = Some(backlight.name());
+            info
+        });
+
+    Some(Monitor {
+        info,
+        backend: Backend::Backlight(backlight),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn internal_panel() -> Option<Monitor> {
+    None
+}
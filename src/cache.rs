@@ -0,0 +1,222 @@
+use ddc_hi::{Display, DisplayInfo};
+use std::{fs, io, path::PathBuf};
+
+use crate::{
+    i2c::{self, bus_path, open},
+    paths,
+};
+
+const HEADER: &str = "# ddc-brightness-ctl display cache v1";
+const FILE_NAME: &str = "displays";
+
+fn cache_path() -> Option<PathBuf> {
+    paths::cache_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+fn read_entries() -> Option<Vec<Entry>> {
+    let contents = fs::read_to_string(cache_path()?).ok()?;
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) {
+        return None;
+    }
+
+    lines.map(Entry::parse).collect()
+}
+
+fn write_entries(path: &std::path::Path, entries: &[Entry]) -> io::Result<()> {
+    let mut contents = format!("{HEADER}\n");
+    for entry in entries {
+        contents.push_str(&entry.format());
+        contents.push('\n');
+    }
+
+    fs::create_dir_all(path.parent().expect("cache path has a parent"))?;
+    fs::write(path, contents)
+}
+
+struct Entry {
+    bus: PathBuf,
+    serial: Option<u32>,
+    model: Option<String>,
+    // VCP features the display has replied that it doesn't support
+    unsupported: Vec<u8>,
+    // VCP features the display's capabilities list, once they have been read
+    supported: Option<Vec<u8>>,
+}
+
+// a comma separated list of hex codes, e.g. 10,12,60
+fn parse_codes(codes: &str) -> Option<Vec<u8>> {
+    codes
+        .split(',')
+        .map(|code| u8::from_str_radix(code, 16).ok())
+        .collect()
+}
+
+fn format_codes(codes: &[u8]) -> String {
+    let codes: Vec<_> = codes.iter().map(|code| format!("{code:02X}")).collect();
+    codes.join(",")
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let bus = PathBuf::from(fields.next()?);
+        let serial = match fields.next()? {
+            "" => None,
+            serial => Some(u32::from_str_radix(serial, 16).ok()?),
+        };
+        let model = match fields.next()? {
+            "" => None,
+            model => Some(model.to_string()),
+        };
+        // added after v1 was released, so may be missing
+        let unsupported = match fields.next() {
+            None | Some("") => Vec::new(),
+            Some(codes) => parse_codes(codes)?,
+        };
+        // an empty list is read back as unknown, so displays listing no features are re-read
+        let supported = match fields.next() {
+            None | Some("") => None,
+            Some(codes) => Some(parse_codes(codes)?),
+        };
+
+        Some(Self {
+            bus,
+            serial,
+            model,
+            unsupported,
+            supported,
+        })
+    }
+
+    fn matches(&self, info: &DisplayInfo) -> bool {
+        self.serial == info.serial
+            && self.model == info.model_name
+            && bus_path(info).as_ref() == Some(&self.bus)
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.bus.display(),
+            self.serial
+                .map(|num| format!("{num:08X}"))
+                .unwrap_or_default(),
+            self.model.as_deref().unwrap_or_default(),
+            format_codes(&self.unsupported),
+            self.supported
+                .as_deref()
+                .map(format_codes)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+// reopens the displays recorded in the cache, skipping any that are no longer connected
+//
+// returns None if there is no usable cache, or if a bus now has a different display
+// attached to it or a display on a bus the cache doesn't know, in which case the displays
+// should be enumerated from scratch
+pub fn load() -> Option<Vec<Display>> {
+    let entries = read_entries()?;
+
+    // reading an EDID from the other buses is much quicker than enumerating, and catches
+    // displays plugged in since the cache was written
+    let new_display = i2c::buses()
+        .unwrap_or_default()
+        .iter()
+        .filter(|bus| !entries.iter().any(|entry| entry.bus == bus.path))
+        .any(i2c::has_display);
+    if new_display {
+        return None;
+    }
+
+    let mut displays = Vec::new();
+    for entry in entries {
+        let Ok(display) = open(&entry.bus) else {
+            continue;
+        };
+
+        if display.info.serial != entry.serial || display.info.model_name != entry.model {
+            return None;
+        }
+
+        displays.push(display);
+    }
+
+    (!displays.is_empty()).then_some(displays)
+}
+
+// records how to reopen the given displays without enumerating them again,
+// nothing is written if any of the displays can't be reopened by its bus path
+pub fn store(displays: &[Display]) -> io::Result<()> {
+    let Some(path) = cache_path() else {
+        return Ok(());
+    };
+
+    let mut entries = Vec::new();
+    for display in displays {
+        let Some(bus) = bus_path(&display.info) else {
+            return Ok(());
+        };
+
+        entries.push(Entry {
+            bus,
+            serial: display.info.serial,
+            model: display.info.model_name.clone(),
+            unsupported: Vec::new(),
+            supported: None,
+        });
+    }
+
+    write_entries(&path, &entries)
+}
+
+/// The VCP features a previous run found the display doesn't support.
+pub fn unsupported_features(info: &DisplayInfo) -> Vec<u8> {
+    read_entries()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|entry| entry.matches(info))
+        .map(|entry| entry.unsupported)
+        .unwrap_or_default()
+}
+
+// records that the display doesn't support a feature, displays which aren't in the cache are
+// left alone as they will be enumerated again on the next run anyway
+pub fn mark_unsupported(info: &DisplayInfo, code: u8) -> io::Result<()> {
+    let (Some(path), Some(mut entries)) = (cache_path(), read_entries()) else {
+        return Ok(());
+    };
+    let Some(entry) = entries.iter_mut().find(|entry| entry.matches(info)) else {
+        return Ok(());
+    };
+    if entry.unsupported.contains(&code) {
+        return Ok(());
+    }
+
+    entry.unsupported.push(code);
+    write_entries(&path, &entries)
+}
+
+/// The VCP features the display's capabilities list, if a previous run read them.
+pub fn supported_features(info: &DisplayInfo) -> Option<Vec<u8>> {
+    read_entries()?
+        .into_iter()
+        .find(|entry| entry.matches(info))?
+        .supported
+}
+
+// records the VCP features the display's capabilities list, like `mark_unsupported` displays
+// which aren't in the cache are left alone
+pub fn store_supported_features(info: &DisplayInfo, codes: &[u8]) -> io::Result<()> {
+    let (Some(path), Some(mut entries)) = (cache_path(), read_entries()) else {
+        return Ok(());
+    };
+    let Some(entry) = entries.iter_mut().find(|entry| entry.matches(info)) else {
+        return Ok(());
+    };
+
+    entry.supported = Some(codes.to_vec());
+    write_entries(&path, &entries)
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs, io,
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{Limits, paths, profile::parse_string};
+
+const FILE_NAME: &str = "config.toml";
+
+/// Settings for one display, identified by its serial number.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DisplayConfig {
+    pub serial: u32,
+    /// The lowest brightness changes may set, as a percentage.
+    pub min: Option<u16>,
+    /// The highest brightness changes may set, as a percentage.
+    pub max: Option<u16>,
+    /// How long to wait for the display to reply to a read.
+    pub timeout: Option<Duration>,
+    /// The lowest brightness relative decreases may go to, as a percentage.
+    pub safe_floor: Option<u16>,
+}
+
+/// Values to set together with `--preset`, on whichever displays are selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PresetConfig {
+    pub name: String,
+    /// Brightness, as a percentage.
+    pub brightness: Option<u16>,
+    /// Contrast, as a percentage.
+    pub contrast: Option<u16>,
+    /// A colour preset, as given to `--color-temp`.
+    pub color_temp: Option<String>,
+}
+
+/// The user's configuration, stored as TOML in `$XDG_CONFIG_HOME/ddc-brightness-ctl/config.toml`.
+///
+/// The top level values are defaults for the command line options of the same names, strings
+/// are left for the caller to parse in the same way as the options.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub display: Option<String>,
+    pub retries: Option<u32>,
+    pub delay: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub color: Option<String>,
+    pub min: Option<u16>,
+    pub max: Option<u16>,
+    pub step: Option<u16>,
+    /// Brightness changes larger than this percentage are confirmed first.
+    pub confirm_delta: Option<u16>,
+    /// What to do about changes to confirm when there's no one to ask, `proceed` or `fail`.
+    pub confirm_unattended: Option<String>,
+    pub location: Option<String>,
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+    pub displays: Vec<DisplayConfig>,
+    /// Names for displays, which can be used to select them, with the serial each stands for.
+    pub aliases: Vec<(String, u32)>,
+    pub presets: Vec<PresetConfig>,
+}
+
+// the table the lines being parsed belong to
+enum Table {
+    Top,
+    Display,
+    Aliases,
+    Preset,
+}
+
+impl Config {
+    /// The serial of the display named `name` in the `[aliases]` table.
+    pub fn alias(&self, name: &str) -> Option<u32> {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .map(|(_, serial)| *serial)
+    }
+
+    pub fn preset(&self, name: &str) -> Option<&PresetConfig> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn display(&self, serial: u32) -> Option<&DisplayConfig> {
+        self.displays
+            .iter()
+            .find(|display| display.serial == serial)
+    }
+
+    /// The brightness limits for the display with `serial`. `min` and `max` take precedence
+    /// when given, then the limits configured for the display, then the top level ones.
+    /// The display's safe floor is left out with `force`.
+    pub fn limits(
+        &self,
+        serial: Option<u32>,
+        min: Option<u16>,
+        max: Option<u16>,
+        force: bool,
+    ) -> Limits {
+        let display = serial.and_then(|serial| self.display(serial));
+        let defaults = Limits::default();
+
+        Limits {
+            min: min
+                .or(display.and_then(|display| display.min))
+                .or(self.min)
+                .unwrap_or(defaults.min),
+            max: max
+                .or(display.and_then(|display| display.max))
+                .or(self.max)
+                .unwrap_or(defaults.max),
+            floor: display
+                .and_then(|display| display.safe_floor)
+                .filter(|_| !force)
+                .unwrap_or(defaults.floor),
+        }
+    }
+
+    /// How long to wait for the display with `serial` to reply to a read, with the same
+    /// precedence as [`Config::limits`].
+    pub fn timeout(&self, serial: Option<u32>, timeout: Option<Duration>) -> Option<Duration> {
+        let display = serial.and_then(|serial| self.display(serial));
+        timeout
+            .or(display.and_then(|display| display.timeout))
+            .or(self.timeout)
+    }
+
+    // the same subset of TOML as profiles: top level values followed by one table per display
+    // named display.SERIAL, an aliases table and one table per preset named preset.NAME,
+    // containing integer and basic string values
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut table = Table::Top;
+        for (line_no, line) in contents.lines().enumerate() {
+            let line_no = line_no + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if header.trim() == "aliases" {
+                    table = Table::Aliases;
+                    continue;
+                }
+                if let Some(name) = header.trim().strip_prefix("preset.") {
+                    if name.is_empty() {
+                        return Err(format!("line {line_no}: expected [preset.NAME]"));
+                    }
+                    config.presets.push(PresetConfig {
+                        name: name.to_string(),
+                        ..PresetConfig::default()
+                    });
+                    table = Table::Preset;
+                    continue;
+                }
+
+                let serial = header
+                    .trim()
+                    .strip_prefix("display.")
+                    .and_then(|serial| u32::from_str_radix(serial, 16).ok())
+                    .ok_or_else(|| {
+                        format!("line {line_no}: expected [display.SERIAL] or [preset.NAME]")
+                    })?;
+                config.displays.push(DisplayConfig {
+                    serial,
+                    ..DisplayConfig::default()
+                });
+                table = Table::Display;
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {line_no}: expected key = value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let percent = || parse_percent(key, value, line_no);
+            let millis = || parse_integer(key, value, line_no).map(Duration::from_millis);
+            if let Table::Aliases = table {
+                let serial = parse_string(value, line_no)?;
+                let serial = u32::from_str_radix(&serial, 16).map_err(|_| {
+                    format!("line {line_no}: invalid serial '{serial}' for alias '{key}'")
+                })?;
+                config.aliases.push((key.to_string(), serial));
+                continue;
+            }
+            if let (Table::Preset, Some(preset)) = (&table, config.presets.last_mut()) {
+                match key {
+                    "brightness" => preset.brightness = Some(percent()?),
+                    "contrast" => preset.contrast = Some(percent()?),
+                    "color-temp" => preset.color_temp = Some(parse_string(value, line_no)?),
+                    _ => return Err(format!("line {line_no}: unknown key '{key}'")),
+                }
+                continue;
+            }
+
+            let display = match table {
+                Table::Display => config.displays.last_mut(),
+                _ => None,
+            };
+            match (display, key) {
+                (None, "display") => config.display = Some(parse_string(value, line_no)?),
+                (None, "retries") => config.retries = Some(parse_integer(key, value, line_no)?),
+                (None, "delay") => config.delay = Some(millis()?),
+                (None, "timeout") => config.timeout = Some(millis()?),
+                (None, "color") => config.color = Some(parse_string(value, line_no)?),
+                (None, "min") => config.min = Some(percent()?),
+                (None, "max") => config.max = Some(percent()?),
+                (None, "step") => config.step = Some(percent()?),
+                (None, "confirm_delta") => config.confirm_delta = Some(percent()?),
+                (None, "confirm_unattended") => {
+                    config.confirm_unattended = Some(parse_string(value, line_no)?)
+                }
+                (None, "location") => config.location = Some(parse_string(value, line_no)?),
+                (None, "sunrise") => config.sunrise = Some(parse_string(value, line_no)?),
+                (None, "sunset") => config.sunset = Some(parse_string(value, line_no)?),
+                (Some(display), "min") => display.min = Some(percent()?),
+                (Some(display), "max") => display.max = Some(percent()?),
+                (Some(display), "timeout") => display.timeout = Some(millis()?),
+                (Some(display), "safe_floor") => display.safe_floor = Some(percent()?),
+                _ => return Err(format!("line {line_no}: unknown key '{key}'")),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_integer<T: FromStr<Err = ParseIntError>>(
+    key: &str,
+    value: &str,
+    line_no: usize,
+) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|e| format!("line {line_no}: invalid {key} '{value}': {e}"))
+}
+
+fn parse_percent(key: &str, value: &str, line_no: usize) -> Result<u16, String> {
+    match value.parse() {
+        Ok(percent @ 0..=100) => Ok(percent),
+        _ => Err(format!(
+            "line {line_no}: invalid {key} '{value}', expected a percentage"
+        )),
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    paths::config_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+/// Loads the configuration from the default path, which is empty if there is no config file.
+pub fn load() -> io::Result<Config> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+
+    match load_from(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        result => result,
+    }
+}
+
+pub fn load_from(path: &Path) -> io::Result<Config> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    Config::parse(&contents).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
@@ -0,0 +1,214 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{Feature, Output, Stream, paths};
+
+const SOCKET_NAME: &str = "daemon.sock";
+// how long a client waits for the daemon before giving up on it
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A relative change forwarded from a client to the daemon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub feature: Feature,
+    pub offset: i16,
+    /// The `--display` selection, unparsed, or None for all displays.
+    pub display: Option<String>,
+}
+
+impl Request {
+    // change CODE OFFSET [DISPLAY], with DISPLAY taking up the rest of the line
+    fn format(&self) -> String {
+        let mut line = format!("change {:02X} {}", self.feature.code(), self.offset);
+        if let Some(display) = &self.display {
+            line.push(' ');
+            line.push_str(display);
+        }
+
+        line
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(4, ' ');
+        if fields.next()? != "change" {
+            return None;
+        }
+        let code = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let offset = fields.next()?.parse().ok()?;
+        let display = fields.next().map(str::to_string);
+
+        Some(Self {
+            feature: Feature::from_code(code),
+            offset,
+            display,
+        })
+    }
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    paths::runtime_dir().map(|dir| dir.join(SOCKET_NAME))
+}
+
+/// Sends the request to the daemon and waits for it to be carried out, returning the output
+/// and whether it succeeded.
+///
+/// Returns None if no daemon is running.
+#[cfg(unix)]
+pub fn forward(request: &Request) -> io::Result<Option<(Output, bool)>> {
+    use std::os::unix::net::UnixStream;
+
+    let Some(path) = socket_path() else {
+        return Ok(None);
+    };
+    let mut stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        // a socket left behind by a daemon which didn't exit cleanly refuses connections
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    writeln!(stream, "{}", request.format())?;
+
+    let mut out = Output::default();
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        match line.split_once(' ') {
+            Some(("out", text)) => out.lines.push((Stream::Stdout, text.to_string())),
+            Some(("err", text)) => out.lines.push((Stream::Stderr, text.to_string())),
+            Some(("status", status)) => return Ok(Some((out, status == "ok"))),
+            _ => break,
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "daemon closed the connection without replying",
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn forward(_request: &Request) -> io::Result<Option<(Output, bool)>> {
+    Ok(None)
+}
+
+/// A client waiting for its request to be carried out.
+#[cfg(unix)]
+pub struct Client {
+    stream: std::os::unix::net::UnixStream,
+}
+
+#[cfg(unix)]
+impl Client {
+    /// Sends the output of carrying out the request back to the client.
+    pub fn reply(mut self, out: &Output, ok: bool) {
+        let mut reply = String::new();
+        for (stream, line) in &out.lines {
+            let prefix = match stream {
+                Stream::Stdout => "out",
+                Stream::Stderr => "err",
+            };
+            // lines can't contain newlines, or they would be split into separate messages
+            reply.push_str(&format!("{prefix} {}\n", line.replace('\n', " ")));
+        }
+        reply.push_str(if ok { "status ok\n" } else { "status failed\n" });
+
+        // the client may have given up waiting, which is no reason to stop the daemon
+        let _ = self.stream.write_all(reply.as_bytes());
+    }
+}
+
+/// The daemon's socket, removed again when dropped.
+#[cfg(unix)]
+pub struct Listener {
+    listener: std::os::unix::net::UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Listener {
+    pub fn bind() -> io::Result<Self> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        let path = socket_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "$XDG_RUNTIME_DIR is not set")
+        })?;
+        if UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a daemon is already listening on {}", path.display()),
+            ));
+        }
+
+        std::fs::create_dir_all(path.parent().expect("socket path has a parent"))?;
+        // anything still here is a stale socket, as nothing answered on it
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self { listener, path })
+    }
+
+    /// Waits for a request, then collects any more which arrive within `window` so that they
+    /// can be carried out together.
+    ///
+    /// Returns None once interrupted with Ctrl-C.
+    pub fn next_batch(&self, window: Duration) -> io::Result<Option<Vec<(Request, Client)>>> {
+        use std::{thread, time::Instant};
+
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let mut batch = Vec::new();
+        let mut deadline = None;
+        loop {
+            if crate::signal::interrupted() {
+                return Ok(None);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(Some(batch));
+            }
+
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+            let mut line = String::new();
+            // clients which send nothing sensible are dropped without a reply
+            if BufReader::new(&stream).read_line(&mut line).is_err() {
+                continue;
+            }
+            let Some(request) = Request::parse(line.trim_end()) else {
+                continue;
+            };
+
+            deadline.get_or_insert_with(|| Instant::now() + window);
+            batch.push((request, Client { stream }));
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
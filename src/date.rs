@@ -0,0 +1,143 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ddc_hi::DisplayInfo;
+
+// EDID years count from 1990
+const EDID_EPOCH: u16 = 1990;
+// the week EDID 1.4 gives to say that the year is the model year rather than when it was made
+const MODEL_YEAR_WEEK: u8 = 0xFF;
+
+/// When a display was made, from its EDID.
+///
+/// The year is taken to count from 1990, as EDID 1.3 and later specify, so displays which
+/// encode it some other way show the wrong year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManufactureDate {
+    Week {
+        week: u8,
+        year: u16,
+    },
+    /// The display doesn't say which week of the year it was made.
+    Year(u16),
+    /// The display gives the model year it was sold as rather than when it was made.
+    ModelYear(u16),
+}
+
+impl ManufactureDate {
+    /// The date from the display's info, or None if it is missing or can't be right, such as
+    /// a week outside 1 to 53 or a year in the future.
+    pub fn of(info: &DisplayInfo) -> Option<Self> {
+        let year = EDID_EPOCH + u16::from(info.manufacture_year?);
+        // displays are often sold as next year's model before the year starts
+        if info.manufacture_week == Some(MODEL_YEAR_WEEK) {
+            return (year <= current_year() + 1).then_some(Self::ModelYear(year));
+        }
+        if year > current_year() {
+            return None;
+        }
+
+        match info.manufacture_week {
+            None | Some(0) => Some(Self::Year(year)),
+            Some(week @ 1..=53) => Some(Self::Week { week, year }),
+            Some(_) => None,
+        }
+    }
+
+    /// The year the display was made, or its model year.
+    pub fn year(self) -> u16 {
+        match self {
+            Self::Week { year, .. } | Self::Year(year) | Self::ModelYear(year) => year,
+        }
+    }
+
+    pub fn week(self) -> Option<u8> {
+        match self {
+            Self::Week { week, .. } => Some(week),
+            Self::Year(_) | Self::ModelYear(_) => None,
+        }
+    }
+
+    pub fn is_model_year(self) -> bool {
+        matches!(self, Self::ModelYear(_))
+    }
+
+    /// The Monday starting the week, taken as an ISO week, as `(year, month, day)`.
+    pub fn approximate_date(self) -> Option<(i64, u32, u32)> {
+        let Self::Week { week, year } = self else {
+            return None;
+        };
+
+        // the 4th of January is always in the first ISO week
+        let january_4th = days_from_civil(i64::from(year), 1, 4);
+        let weekday = (january_4th + 3).rem_euclid(7);
+        let monday = january_4th - weekday + 7 * (i64::from(week) - 1);
+        Some(civil_from_days(monday))
+    }
+}
+
+/// Converts days since the Unix epoch to a `(year, month, day)` date, see
+/// <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month as u32, day as u32)
+}
+
+// the inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+fn current_year() -> u16 {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86400);
+    civil_from_days(days as i64).0 as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(week: u8, year: u8) -> Option<ManufactureDate> {
+        let mut info = DisplayInfo::new(ddc_hi::Backend::I2cDevice, "test".to_string());
+        info.manufacture_week = Some(week);
+        info.manufacture_year = Some(year);
+        ManufactureDate::of(&info)
+    }
+
+    #[test]
+    fn weeks_count_years_from_1990() {
+        assert_eq!(
+            date(12, 31),
+            Some(ManufactureDate::Week {
+                week: 12,
+                year: 2021
+            })
+        );
+        assert_eq!(date(0, 31), Some(ManufactureDate::Year(2021)));
+        assert_eq!(date(54, 31), None);
+    }
+
+    #[test]
+    fn week_0xff_gives_a_model_year() {
+        assert_eq!(date(0xFF, 31), Some(ManufactureDate::ModelYear(2021)));
+        assert_eq!(date(0xFF, 0), Some(ManufactureDate::ModelYear(1990)));
+        assert_eq!(date(0xFF, 31).and_then(ManufactureDate::week), None);
+    }
+}
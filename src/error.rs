@@ -0,0 +1,178 @@
+use ddc::DdcHost;
+use ddc_hi::Handle;
+use std::{error, fmt, io, time::Duration};
+
+use crate::{Feature, PowerState};
+
+/// The underlying error returned by ddc-hi when communicating with a display.
+pub type DdcError = <Handle as DdcHost>::Error;
+
+/// What went wrong talking to a display, which decides whether a command is worth retrying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The display replied that it doesn't support the feature.
+    Unsupported,
+    /// No reply, or a garbled one, came back in time.
+    Timeout,
+    /// The display didn't acknowledge the command on the bus.
+    Nak,
+    /// The bus can't be used at all, e.g. for lack of permission or because it has gone away.
+    Bus,
+    /// The error couldn't be told apart, which happens on platforms other than Linux.
+    Unknown,
+}
+
+impl ErrorKind {
+    pub fn of(e: &DdcError) -> Self {
+        classify(&**e)
+    }
+
+    /// Whether the failure may well not happen again, so the command can be retried.
+    pub fn is_transient(self) -> bool {
+        matches!(self, Self::Timeout | Self::Nak | Self::Unknown)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn classify(e: &(dyn error::Error + 'static)) -> ErrorKind {
+    use ddc::ErrorCode;
+
+    let Some(e) = e.downcast_ref::<ddc_i2c::Error<io::Error>>() else {
+        return ErrorKind::Unknown;
+    };
+    match e {
+        ddc_i2c::Error::Ddc(ErrorCode::Invalid(msg)) if msg == "Unsupported VCP code" => {
+            ErrorKind::Unsupported
+        }
+        ddc_i2c::Error::Ddc(_) => ErrorKind::Timeout,
+        ddc_i2c::Error::I2c(e) => match (e.kind(), e.raw_os_error()) {
+            (io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock, _) => ErrorKind::Timeout,
+            (_, Some(libc::ENXIO | libc::EREMOTEIO | libc::EIO)) => ErrorKind::Nak,
+            _ => ErrorKind::Bus,
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn classify(_e: &(dyn error::Error + 'static)) -> ErrorKind {
+    ErrorKind::Unknown
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The display didn't respond to a read within the time waited for it.
+    Timeout(Duration, DdcError),
+    /// The display didn't respond to the first command sent to it, so likely doesn't have
+    /// DDC/CI enabled.
+    DdcUnavailable(DdcError),
+    /// Talking to the display failed other than by timing out, see [`ErrorKind`].
+    Communication(ErrorKind, DdcError),
+    /// Writing a new value for the feature failed.
+    SetFailed(Feature, DdcError),
+    /// Switching the display to the power state failed.
+    PowerFailed(PowerState, DdcError),
+    /// The display replied that it doesn't support the feature.
+    UnsupportedFeature(Feature),
+    /// The display's capabilities don't list the value for the feature.
+    UnsupportedValue {
+        feature: Feature,
+        value: u16,
+        supported: Vec<u16>,
+    },
+    /// Ctrl-C was pressed while fading the feature, which was put back as it was.
+    Interrupted(Feature),
+    /// A large change to the feature wasn't confirmed, so it wasn't made.
+    Declined(Feature),
+    /// Several colour channels, or features of a preset, couldn't be set, with the error for each.
+    Channels(Vec<Error>),
+    /// Reading or parsing the capabilities string failed.
+    Capabilities(DdcError),
+    /// Reading the sysfs backlight failed.
+    Backlight(io::Error),
+    /// No connected display matched the selection, described by the string.
+    NoSuchDisplay(String),
+    /// The command line arguments were invalid.
+    ParseArgs(lexopt::Error),
+    /// The config file couldn't be read or is invalid.
+    Config(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout(timeout, _) => {
+                write!(
+                    f,
+                    "timed out waiting {}ms for response",
+                    timeout.as_millis()
+                )
+            }
+            Error::DdcUnavailable(_) => f.write_str(
+                "no response over DDC/CI, it may be disabled in the display's on-screen menu",
+            ),
+            Error::Communication(kind, e) => match kind {
+                ErrorKind::Nak => write!(f, "command not acknowledged: {e}"),
+                ErrorKind::Bus => write!(f, "I2C bus error: {e}"),
+                _ => write!(f, "communication failed: {e}"),
+            },
+            Error::SetFailed(feature, e) => write!(f, "failed to set {}: {e}", feature.name()),
+            Error::PowerFailed(state, e) => {
+                write!(f, "failed to switch power to {}: {e}", state.name())
+            }
+            Error::UnsupportedFeature(feature) => write!(
+                f,
+                "{} not supported (VCP 0x{:02X})",
+                feature.name(),
+                feature.code()
+            ),
+            Error::UnsupportedValue { feature, value, .. } => write!(
+                f,
+                "{} {} not supported",
+                feature.name(),
+                feature.value_name(*value)
+            ),
+            Error::Interrupted(feature) => {
+                write!(f, "interrupted changing {}, restored it", feature.name())
+            }
+            Error::Declined(feature) => {
+                write!(f, "change of {} not confirmed", feature.name())
+            }
+            Error::Channels(errors) => {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                f.write_str(&errors.join(", "))
+            }
+            Error::Capabilities(e) => write!(f, "failed to read capabilities: {e}"),
+            Error::Backlight(e) => write!(f, "failed to read backlight: {e}"),
+            Error::NoSuchDisplay(selection) => write!(f, "no display {selection}"),
+            Error::ParseArgs(e) => write!(f, "failed to parse arguments: {e}"),
+            Error::Config(e) => write!(f, "failed to load config: {e}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Timeout(_, e)
+            | Error::DdcUnavailable(e)
+            | Error::Communication(_, e)
+            | Error::SetFailed(_, e)
+            | Error::PowerFailed(_, e)
+            | Error::Capabilities(e) => Some(e.as_ref()),
+            Error::Backlight(e) | Error::Config(e) => Some(e),
+            Error::ParseArgs(e) => Some(e),
+            Error::UnsupportedFeature(_)
+            | Error::UnsupportedValue { .. }
+            | Error::Interrupted(_)
+            | Error::Declined(_)
+            | Error::Channels(_)
+            | Error::NoSuchDisplay(_) => None,
+        }
+    }
+}
+
+impl From<lexopt::Error> for Error {
+    fn from(e: lexopt::Error) -> Self {
+        Error::ParseArgs(e)
+    }
+}
@@ -0,0 +1,263 @@
+use ddc_hi::{Display, DisplayInfo};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const DEV_DIR: &str = "/dev";
+const SYS_DIR: &str = "/sys/bus/i2c/devices";
+#[cfg(target_os = "linux")]
+const DRM_DIR: &str = "/sys/class/drm";
+
+/// An i2c-dev device node, which a display may be connected to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bus {
+    pub path: PathBuf,
+    /// The name the kernel gives the adapter, e.g. `i915 gmbus dpb`, when it has one.
+    pub name: Option<String>,
+}
+
+/// The `/dev/i2c-N` device nodes, in order of N.
+pub fn buses() -> io::Result<Vec<Bus>> {
+    let mut buses: Vec<_> = fs::read_dir(DEV_DIR)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let number: u32 = file_name.strip_prefix("i2c-")?.parse().ok()?;
+            let name = fs::read_to_string(Path::new(SYS_DIR).join(&file_name).join("name"))
+                .ok()
+                .map(|name| name.trim().to_string());
+            Some((
+                number,
+                Bus {
+                    path: entry.path(),
+                    name,
+                },
+            ))
+        })
+        .collect();
+    buses.sort_by_key(|(number, _)| *number);
+
+    Ok(buses.into_iter().map(|(_, bus)| bus).collect())
+}
+
+/// A DRM connector, which a display is plugged into, e.g. `DP-1` or `HDMI-A-1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Connector {
+    pub name: String,
+    /// The i2c-dev node of the connector's DDC bus, which some connectors don't have.
+    pub bus: Option<PathBuf>,
+}
+
+/// The connectors of every graphics card, sorted by card and name.
+#[cfg(target_os = "linux")]
+pub fn connectors() -> io::Result<Vec<Connector>> {
+    let mut connectors: Vec<_> = fs::read_dir(DRM_DIR)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            // connectors are named after their card, e.g. card0-DP-1
+            let file_name = entry.file_name().into_string().ok()?;
+            let (card, name) = file_name.split_once('-')?;
+            card.strip_prefix("card")?.parse::<u32>().ok()?;
+            // the ddc link points at the bus's i2c adapter, e.g. .../i2c-5
+            let bus = fs::read_link(entry.path().join("ddc"))
+                .ok()
+                .and_then(|link| link.file_name().map(|name| name.to_owned()))
+                .filter(|adapter| adapter.to_string_lossy().starts_with("i2c-"))
+                .map(|adapter| Path::new(DEV_DIR).join(adapter));
+            Some((
+                file_name.clone(),
+                Connector {
+                    name: name.to_string(),
+                    bus,
+                },
+            ))
+        })
+        .collect();
+    connectors.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(connectors
+        .into_iter()
+        .map(|(_, connector)| connector)
+        .collect())
+}
+
+// connectors are found through DRM, which only Linux has
+#[cfg(not(target_os = "linux"))]
+pub fn connectors() -> io::Result<Vec<Connector>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Looks up the connector named `name`, ignoring case, if there is one.
+pub fn connector(name: &str) -> Option<Connector> {
+    connectors()
+        .ok()?
+        .into_iter()
+        .find(|connector| connector.name.eq_ignore_ascii_case(name))
+}
+
+/// Parses a bus given as `N` or a path such as `/dev/i2c-N`.
+pub fn parse_bus(s: &str) -> Result<PathBuf, String> {
+    match s.parse::<u32>() {
+        Ok(number) => Ok(Path::new(DEV_DIR).join(format!("i2c-{number}"))),
+        Err(_) if s.contains('/') => Ok(PathBuf::from(s)),
+        Err(_) => Err(format!("invalid bus '{s}', expected N or /dev/i2c-N")),
+    }
+}
+
+/// Opens the display on a bus directly, without enumerating any others.
+///
+/// The display's info comes from its EDID, or is left empty if the EDID can't be read.
+#[cfg(target_os = "linux")]
+pub fn open(bus: &Path) -> io::Result<Display> {
+    use ddc::Edid;
+    use ddc_hi::{Backend, Handle};
+    use std::os::unix::fs::MetadataExt;
+
+    let mut ddc = ddc_i2c::from_i2c_device(bus)?;
+    let id = ddc.inner_ref().inner_ref().metadata()?.rdev().to_string();
+    let mut edid = vec![0u8; 0x100];
+    let info = match ddc.read_edid(0, &mut edid) {
+        Ok(_) => DisplayInfo::from_edid(Backend::I2cDevice, id, edid)?,
+        Err(_) => DisplayInfo::new(Backend::I2cDevice, id),
+    };
+
+    Ok(Display::new(Handle::I2cDevice(ddc), info))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn open(_bus: &Path) -> io::Result<Display> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Whether a display answers with an EDID on the bus, checked the way enumerating displays
+/// does, including skipping the adapters it leaves alone.
+pub fn has_display(bus: &Bus) -> bool {
+    // the adapters ddc-i2c skips, as they aren't display connectors
+    const SKIPPED: [&str; 5] = ["SMBus", "soc:i2cdsi", "smu", "mac-io", "u4"];
+    let Some(name) = &bus.name else {
+        return false;
+    };
+    if SKIPPED.iter().any(|prefix| name.starts_with(prefix)) {
+        return false;
+    }
+
+    // an EDID which doesn't parse fails to open, as enumerating skips those too
+    open(&bus.path).is_ok_and(|display| display.info.edid_data.is_some())
+}
+
+/// The device node of the bus a display was found on, i2c-dev displays are identified by the
+/// device number of their `/dev/i2c-N` node.
+#[cfg(target_os = "linux")]
+pub fn bus_path(info: &DisplayInfo) -> Option<PathBuf> {
+    use ddc_hi::Backend;
+    use std::os::unix::fs::MetadataExt;
+
+    if info.backend != Backend::I2cDevice {
+        return None;
+    }
+
+    let id: u64 = info.id.parse().ok()?;
+    fs::read_dir(DEV_DIR)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("i2c-"))
+        .find(|entry| entry.metadata().is_ok_and(|meta| meta.rdev() == id))
+        .map(|entry| entry.path())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn bus_path(_info: &DisplayInfo) -> Option<PathBuf> {
+    None
+}
+
+/// Why displays can't be reached over i2c-dev, for the usual problems on a first run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AccessProblem {
+    /// There are no `/dev/i2c-N` nodes at all, as the i2c-dev module isn't loaded.
+    NotLoaded,
+    /// Some nodes exist but can't be opened by this user.
+    PermissionDenied {
+        buses: Vec<PathBuf>,
+        /// The group owning the nodes, which users are added to for access, or None when it
+        /// is root's and there isn't one to join.
+        group: Option<String>,
+    },
+}
+
+impl AccessProblem {
+    /// What went wrong and how to fix it, for the user to read.
+    pub fn advice(&self) -> String {
+        match self {
+            Self::NotLoaded => "there are no /dev/i2c-* devices, load the i2c-dev module with \
+                                `sudo modprobe i2c-dev`, and add i2c-dev to a file in \
+                                /etc/modules-load.d to load it at boot"
+                .to_string(),
+            Self::PermissionDenied { buses, group } => {
+                let buses: Vec<_> = buses.iter().map(|bus| bus.display().to_string()).collect();
+                let fix = match group {
+                    Some(group) => format!(
+                        "add yourself to the {group} group with `sudo usermod -aG {group} \
+                         $USER` and log in again"
+                    ),
+                    None => "only root can open them, add a udev rule such as \
+                             KERNEL==\"i2c-[0-9]*\", GROUP=\"i2c\", MODE=\"0660\" and add \
+                             yourself to the i2c group"
+                        .to_string(),
+                };
+                format!("permission denied opening {}, {fix}", buses.join(", "))
+            }
+        }
+    }
+}
+
+/// Checks whether the i2c-dev nodes are missing or can't be opened, which enumerating displays
+/// silently skips.
+///
+/// Opening a node doesn't send anything over the bus, so this is safe to call at any time.
+#[cfg(target_os = "linux")]
+pub fn access_problem() -> Option<AccessProblem> {
+    use std::os::unix::fs::MetadataExt;
+
+    let buses = buses().unwrap_or_default();
+    if buses.is_empty() {
+        return Some(AccessProblem::NotLoaded);
+    }
+
+    let denied: Vec<_> = buses
+        .into_iter()
+        .map(|bus| bus.path)
+        .filter(|path| {
+            let open = fs::OpenOptions::new().read(true).write(true).open(path);
+            open.is_err_and(|e| e.kind() == io::ErrorKind::PermissionDenied)
+        })
+        .collect();
+    let group = fs::metadata(denied.first()?)
+        .ok()
+        .map(|meta| meta.gid())
+        .filter(|&gid| gid != 0)
+        .and_then(group_name);
+
+    Some(AccessProblem::PermissionDenied {
+        buses: denied,
+        group,
+    })
+}
+
+// i2c-dev is Linux only, and other platforms don't have anything to diagnose
+#[cfg(not(target_os = "linux"))]
+pub fn access_problem() -> Option<AccessProblem> {
+    None
+}
+
+// looks the group up in /etc/group, which is enough for the local groups device nodes belong to
+#[cfg(target_os = "linux")]
+fn group_name(gid: u32) -> Option<String> {
+    let groups = fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let id: u32 = fields.nth(1)?.parse().ok()?;
+        (id == gid).then(|| name.to_string())
+    })
+}
@@ -0,0 +1,2800 @@
+use backlight::Backlight;
+use ddc::{Ddc, DdcHost, VcpValue};
+use ddc_hi::{Display, DisplayInfo, Handle};
+use json::Json;
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub mod backlight;
+pub mod cache;
+pub mod config;
+pub mod daemon;
+pub mod date;
+mod error;
+pub mod i2c;
+pub mod json;
+pub mod list;
+pub mod lock;
+pub mod log;
+#[cfg(test)]
+mod mock;
+pub mod notify;
+mod paths;
+pub mod profile;
+pub mod progress;
+pub mod schedule;
+pub mod signal;
+pub mod timing;
+pub mod undo;
+
+pub use error::{DdcError, Error, ErrorKind};
+
+pub const DEFAULT_RETRIES: u32 = 2;
+/// How long DDC/CI specifies waiting for a display to reply to a read.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(40);
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const IDENTIFY_FLASHES: u32 = 2;
+const IDENTIFY_FLASH_DURATION: Duration = Duration::from_millis(400);
+// the curve `--perceptual` steps along, the same as sRGB's approximate gamma
+const PERCEPTUAL_GAMMA: f32 = 2.2;
+
+pub const LUMINANCE_FEATURE_CODE: u8 = 0x10;
+pub const CONTRAST_FEATURE_CODE: u8 = 0x12;
+pub const INPUT_SOURCE_FEATURE_CODE: u8 = 0x60;
+pub const POWER_MODE_FEATURE_CODE: u8 = 0xD6;
+pub const COLOR_PRESET_FEATURE_CODE: u8 = 0x14;
+pub const RED_GAIN_FEATURE_CODE: u8 = 0x16;
+pub const GREEN_GAIN_FEATURE_CODE: u8 = 0x18;
+pub const BLUE_GAIN_FEATURE_CODE: u8 = 0x1A;
+pub const FACTORY_RESET_FEATURE_CODE: u8 = 0x04;
+pub const VOLUME_FEATURE_CODE: u8 = 0x62;
+
+// names for the standard MCCS input source values, usbc is not standardised
+// but 0x1B is what most monitors with a USB-C input seem to use
+const INPUT_SOURCES: &[(&str, u16)] = &[
+    ("vga1", 0x01),
+    ("vga2", 0x02),
+    ("dvi1", 0x03),
+    ("dvi2", 0x04),
+    ("composite1", 0x05),
+    ("composite2", 0x06),
+    ("svideo1", 0x07),
+    ("svideo2", 0x08),
+    ("tuner1", 0x09),
+    ("tuner2", 0x0A),
+    ("tuner3", 0x0B),
+    ("component1", 0x0C),
+    ("component2", 0x0D),
+    ("component3", 0x0E),
+    ("dp1", 0x0F),
+    ("dp2", 0x10),
+    ("hdmi1", 0x11),
+    ("hdmi2", 0x12),
+    ("usbc", 0x1B),
+];
+
+// the MCCS colour temperature presets, user presets are whatever the display's menu stores
+const COLOR_PRESETS: &[(&str, u16)] = &[
+    ("srgb", 0x01),
+    ("native", 0x02),
+    ("4000k", 0x03),
+    ("5000k", 0x04),
+    ("6500k", 0x05),
+    ("7500k", 0x06),
+    ("8200k", 0x07),
+    ("9300k", 0x08),
+    ("10000k", 0x09),
+    ("11500k", 0x0A),
+    ("user1", 0x0B),
+    ("user2", 0x0C),
+    ("user3", 0x0D),
+];
+
+/// Settings which affect how an [`Action`] is carried out on each display.
+#[derive(Clone, Copy)]
+pub struct Options {
+    pub json: bool,
+    pub fade: Option<Fade>,
+    pub retries: u32,
+    /// Overrides the delay waited between DDC commands.
+    pub delay: Option<Duration>,
+    /// Overrides how long to wait for a display to reply to a read, see [`DEFAULT_TIMEOUT`].
+    pub timeout: Option<Duration>,
+    /// Whether error messages should be highlighted with ANSI colours.
+    pub color: bool,
+    /// Reads displays and reports what would be changed, without writing anything.
+    pub dry_run: bool,
+    /// Bounds brightness changes are clamped to.
+    pub limits: Limits,
+    /// Reads and changes brightness in the display's own units rather than as a percentage.
+    pub raw: bool,
+    /// Applies relative changes along a perceptual curve, see [`BrightnessChange::apply_perceptual`].
+    pub perceptual: bool,
+    /// How [`Action::Get`] prints the brightness, unless printing JSON.
+    pub format: GetFormat,
+    /// Asks before large brightness changes, unless given `--yes`.
+    pub confirm: Option<ConfirmJumps>,
+    /// Reads features back after changing them, to catch displays which ignore or round writes.
+    pub verify: bool,
+}
+
+/// Asks for confirmation before changing the brightness by more than `delta` percent, to catch
+/// typos such as `--set 5` for `--set 50`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfirmJumps {
+    pub delta: u16,
+    /// Whether to go ahead, rather than leave the display alone, when stdin isn't a terminal
+    /// so there is no one to ask.
+    pub proceed_unattended: bool,
+}
+
+/// Spreads a change out over time rather than jumping straight to the new value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fade {
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+/// The curve a [`Fade`] follows from the old value to the new one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Changes at the same rate throughout.
+    #[default]
+    Linear,
+    /// Starts slowly and speeds up.
+    EaseIn,
+    /// Starts quickly and slows down towards the new value.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "ease-in" => Ok(Self::EaseIn),
+            "ease-out" => Ok(Self::EaseOut),
+            "ease-in-out" => Ok(Self::EaseInOut),
+            _ => Err(format!(
+                "invalid easing '{s}', expected linear, ease-in, ease-out or ease-in-out"
+            )),
+        }
+    }
+
+    // how much of the change has been made `t` of the way through the fade, both from 0 to 1
+    fn progress(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Self::EaseInOut => 1.0 - 2.0 * (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+/// How [`Action::Get`] prints the brightness read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GetFormat {
+    /// A sentence naming the display.
+    #[default]
+    Text,
+    /// Just the number, for status bars and scripts.
+    Value,
+    /// A gauge drawn with block characters, always of the percentage.
+    Bar,
+}
+
+impl GetFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Self::Text),
+            "value" => Ok(Self::Value),
+            "bar" => Ok(Self::Bar),
+            _ => Err(format!("invalid format '{s}', expected text, value or bar")),
+        }
+    }
+}
+
+// the features `--get` can read, by the name each is given and shown under
+const GET_FEATURES: &[(&str, Feature)] = &[
+    ("brightness", Feature::Luminance),
+    ("contrast", Feature::Contrast),
+    ("input", Feature::InputSource),
+    ("power", Feature::PowerMode),
+    ("color-temp", Feature::ColorPreset),
+    ("red", Feature::RedGain),
+    ("green", Feature::GreenGain),
+    ("blue", Feature::BlueGain),
+    ("volume", Feature::Volume),
+];
+
+/// The features read by [`Action::Get`], in the order they were asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GetFeatures {
+    features: [Feature; GET_FEATURES.len()],
+    len: usize,
+}
+
+impl Default for GetFeatures {
+    fn default() -> Self {
+        Self {
+            features: [Feature::Luminance; GET_FEATURES.len()],
+            len: 1,
+        }
+    }
+}
+
+impl GetFeatures {
+    /// Parses a comma separated list of feature names, e.g. `brightness,contrast,input`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut features = Self {
+            len: 0,
+            ..Self::default()
+        };
+        for name in s.split(',').map(str::trim) {
+            let Some(&(_, feature)) = GET_FEATURES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            else {
+                let names: Vec<_> = GET_FEATURES.iter().map(|(n, _)| *n).collect();
+                return Err(format!(
+                    "unknown feature '{name}', expected one of {}",
+                    names.join(", ")
+                ));
+            };
+            // a feature given twice is only read once, so the list never outgrows the array
+            if !features.iter().any(|f| f == feature) {
+                features.features[features.len] = feature;
+                features.len += 1;
+            }
+        }
+
+        Ok(features)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Feature> + '_ {
+        self.features[..self.len].iter().copied()
+    }
+
+    /// Whether only brightness is read, which is printed the same way as a plain `--get`.
+    pub fn is_brightness(&self) -> bool {
+        self.len == 1 && self.features[0] == Feature::Luminance
+    }
+}
+
+// the width of a `GetFormat::Bar` gauge in characters
+const BAR_WIDTH: u16 = 10;
+// the blocks partly filled cells are drawn with, by how many eighths are filled
+const BAR_EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+// draws `percent` as a gauge BAR_WIDTH characters wide, e.g. `█████▌    ` for 55
+fn bar(percent: u16) -> String {
+    let eighths = u32::from(percent.min(100)) * u32::from(BAR_WIDTH) * 8 / 100;
+    let (full, part) = ((eighths / 8) as usize, (eighths % 8) as usize);
+    let mut bar = "█".repeat(full);
+    if full < usize::from(BAR_WIDTH) {
+        bar.push(BAR_EIGHTHS[part]);
+        bar.push_str(&" ".repeat(usize::from(BAR_WIDTH) - full - 1));
+    }
+
+    bar
+}
+
+/// Soft limits on the brightness, as percentages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub min: u16,
+    pub max: u16,
+    /// The lowest brightness relative decreases may go to, unlike `min` absolute changes can
+    /// still go below it.
+    pub floor: u16,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: 100,
+            floor: 0,
+        }
+    }
+}
+
+impl Limits {
+    // clamps a raw value out of `maximum`, if the limits cross the maximum wins
+    fn clamp(self, value: u16, maximum: u16) -> u16 {
+        value
+            .max(from_percent(self.min, maximum))
+            .min(from_percent(self.max.min(100), maximum))
+    }
+}
+
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Output buffered per display so it can be printed in order once all displays are done.
+#[derive(Default)]
+pub struct Output {
+    lines: Vec<(Stream, String)>,
+    // the value a change started from, once it has been read
+    before: Option<u16>,
+    // progress messages would break up the JSON printed with --json
+    json: bool,
+}
+
+impl Output {
+    /// Output for a display acted on with `options`, which leaves out progress messages with
+    /// `--json`.
+    pub fn new(options: Options) -> Self {
+        Self {
+            json: options.json,
+            ..Self::default()
+        }
+    }
+
+    /// The value read before changing a feature, as shown in the display's messages.
+    pub fn before(&self) -> Option<u16> {
+        self.before
+    }
+
+    pub fn println(&mut self, args: fmt::Arguments) {
+        self.lines.push((Stream::Stdout, args.to_string()));
+    }
+
+    /// Adds a progress message, which is left out with `--quiet` and `--json`.
+    pub fn info(&mut self, args: fmt::Arguments) {
+        if !self.json && log::enabled(log::Level::Info) {
+            self.println(args);
+        }
+    }
+
+    pub fn eprintln(&mut self, args: fmt::Arguments) {
+        self.lines.push((Stream::Stderr, args.to_string()));
+    }
+
+    pub fn flush(self) {
+        for (stream, line) in self.lines {
+            match stream {
+                Stream::Stdout => println!("{line}"),
+                Stream::Stderr => eprintln!("{line}"),
+            }
+        }
+    }
+}
+
+/// How a display's settings are read and changed.
+pub enum Backend {
+    /// DDC/CI over the display's video connection.
+    Ddc(Handle),
+    /// The kernel's backlight interface for internal panels, which only controls brightness.
+    Backlight(Backlight),
+    /// Any other way of reaching a display, such as an in-memory one for tests.
+    Custom(Box<dyn BrightnessBackend>),
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ddc(_) => "ddc",
+            Self::Backlight(_) => "backlight",
+            Self::Custom(backend) => backend.name(),
+        }
+    }
+}
+
+/// Reads and writes the features of a display which isn't reached over DDC/CI or a backlight.
+///
+/// Only features read with [`BrightnessBackend::read`] can be changed, anything which needs
+/// capabilities or writes blindly is treated as unsupported. Retries and delays are left to
+/// the backend.
+pub trait BrightnessBackend: Send {
+    /// Describes the backend in `--list`, in the same way as `ddc` and `backlight`.
+    fn name(&self) -> &'static str;
+
+    /// Reads the raw value and maximum of `feature`.
+    fn read(&mut self, feature: Feature) -> Result<(u16, u16), Error>;
+
+    /// Writes a raw value to the feature with `code`.
+    fn write(&mut self, code: u8, value: u16) -> Result<(), DdcError>;
+}
+
+/// A connected display, controlled through whichever backend suits it.
+pub struct Monitor {
+    pub info: DisplayInfo,
+    pub backend: Backend,
+}
+
+impl From<Display> for Monitor {
+    fn from(display: Display) -> Self {
+        Self {
+            info: display.info,
+            backend: Backend::Ddc(display.handle),
+        }
+    }
+}
+
+impl Monitor {
+    /// Finds all connected displays, see [`Monitor::from_displays`].
+    pub fn enumerate() -> Vec<Self> {
+        Self::from_displays(Display::enumerate())
+    }
+
+    /// Wraps displays found over DDC/CI, adding the internal panel's backlight if there is one.
+    ///
+    /// Internal panels rarely answer over DDC/CI, so if one was found anyway it is replaced
+    /// by its backlight.
+    pub fn from_displays(displays: Vec<Display>) -> Vec<Self> {
+        let internal = backlight::internal_panel();
+        let mut monitors: Vec<Self> = displays
+            .into_iter()
+            .filter(|display| {
+                internal
+                    .as_ref()
+                    .is_none_or(|internal| !same_panel(&internal.info, &display.info))
+            })
+            .map(Self::from)
+            .collect();
+        monitors.extend(internal);
+
+        monitors
+    }
+
+    // features other than brightness are only reachable over DDC/CI
+    fn ddc(&mut self, feature: Feature) -> Result<&mut Handle, Error> {
+        match &mut self.backend {
+            Backend::Ddc(handle) => Ok(handle),
+            Backend::Backlight(_) | Backend::Custom(_) => Err(Error::UnsupportedFeature(feature)),
+        }
+    }
+}
+
+/// Drops displays found more than once, such as through two I2C buses of the same GPU, keeping
+/// the first of each which answers over DDC/CI.
+///
+/// Displays are the same if their EDIDs give the same model and serial number, those without
+/// a serial can't be told apart from another of the same model so are all kept.
+pub fn dedupe(displays: Vec<Display>) -> Vec<Display> {
+    let mut kept: Vec<Display> = Vec::new();
+    for mut display in displays {
+        let Some(first) = kept
+            .iter_mut()
+            .find(|kept| same_display(&kept.info, &display.info))
+        else {
+            kept.push(display);
+            continue;
+        };
+
+        // only duplicates are probed, so displays found once cost nothing extra
+        let (dropped, kept_id) = if !probe(&mut first.handle) && probe(&mut display.handle) {
+            let dropped = std::mem::replace(first, display);
+            (dropped.info.id, &first.info.id)
+        } else {
+            (display.info.id, &first.info.id)
+        };
+        log::debug(format_args!(
+            "Dropping {dropped}, the same display as {kept_id}"
+        ));
+    }
+
+    kept
+}
+
+// some displays give every unit a serial of zero, so that isn't taken to identify one
+fn same_display(a: &DisplayInfo, b: &DisplayInfo) -> bool {
+    a.serial.is_some_and(|serial| serial != 0)
+        && a.serial == b.serial
+        && a.manufacturer_id == b.manufacturer_id
+        && a.model_id == b.model_id
+        && a.model_name == b.model_name
+}
+
+// the backlight's info only has an EDID to compare when the kernel exposes one
+fn same_panel(a: &DisplayInfo, b: &DisplayInfo) -> bool {
+    a.serial.is_some()
+        && a.manufacturer_id == b.manufacturer_id
+        && a.model_id == b.model_id
+        && a.serial == b.serial
+}
+
+/// A VCP feature which can be read and changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Luminance,
+    Contrast,
+    InputSource,
+    PowerMode,
+    ColorPreset,
+    RedGain,
+    GreenGain,
+    BlueGain,
+    /// The volume of the display's speakers or audio output.
+    Volume,
+    /// Restores factory defaults when written, it has no value to read.
+    FactoryReset,
+    /// Any other feature, accessed by its raw VCP code.
+    Vcp(u8),
+}
+
+impl Feature {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            LUMINANCE_FEATURE_CODE => Self::Luminance,
+            CONTRAST_FEATURE_CODE => Self::Contrast,
+            INPUT_SOURCE_FEATURE_CODE => Self::InputSource,
+            POWER_MODE_FEATURE_CODE => Self::PowerMode,
+            COLOR_PRESET_FEATURE_CODE => Self::ColorPreset,
+            RED_GAIN_FEATURE_CODE => Self::RedGain,
+            GREEN_GAIN_FEATURE_CODE => Self::GreenGain,
+            BLUE_GAIN_FEATURE_CODE => Self::BlueGain,
+            FACTORY_RESET_FEATURE_CODE => Self::FactoryReset,
+            VOLUME_FEATURE_CODE => Self::Volume,
+            code => Self::Vcp(code),
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Luminance => LUMINANCE_FEATURE_CODE,
+            Self::Contrast => CONTRAST_FEATURE_CODE,
+            Self::InputSource => INPUT_SOURCE_FEATURE_CODE,
+            Self::PowerMode => POWER_MODE_FEATURE_CODE,
+            Self::ColorPreset => COLOR_PRESET_FEATURE_CODE,
+            Self::RedGain => RED_GAIN_FEATURE_CODE,
+            Self::GreenGain => GREEN_GAIN_FEATURE_CODE,
+            Self::BlueGain => BLUE_GAIN_FEATURE_CODE,
+            Self::FactoryReset => FACTORY_RESET_FEATURE_CODE,
+            Self::Volume => VOLUME_FEATURE_CODE,
+            Self::Vcp(code) => code,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Luminance => "brightness",
+            Self::Contrast => "contrast",
+            Self::InputSource => "input source",
+            Self::PowerMode => "power mode",
+            Self::ColorPreset => "colour preset",
+            Self::RedGain => "red gain",
+            Self::GreenGain => "green gain",
+            Self::BlueGain => "blue gain",
+            Self::FactoryReset => "factory defaults",
+            Self::Volume => "volume",
+            Self::Vcp(_) => "VCP feature",
+        }
+    }
+
+    /// Describes a value of the feature, e.g. `hdmi1` for an input source.
+    pub fn value_name(self, value: u16) -> String {
+        let names = match self {
+            Self::InputSource => INPUT_SOURCES,
+            Self::ColorPreset => COLOR_PRESETS,
+            _ => &[],
+        };
+
+        names
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(n, _)| n.to_string())
+            .unwrap_or_else(|| format!("0x{value:02X}"))
+    }
+}
+
+/// A DPMS power state, as written to VCP feature 0xD6.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    On,
+    Standby,
+    Suspend,
+    Off,
+}
+
+impl PowerState {
+    const ALL: [Self; 4] = [Self::On, Self::Standby, Self::Suspend, Self::Off];
+
+    pub fn value(self) -> u16 {
+        match self {
+            Self::On => 0x01,
+            Self::Standby => 0x02,
+            Self::Suspend => 0x03,
+            Self::Off => 0x04,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::On => "on",
+            Self::Standby => "standby",
+            Self::Suspend => "suspend",
+            Self::Off => "off",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|state| state.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown power state '{s}', expected on, standby, suspend or off")
+            })
+    }
+}
+
+/// Video gains for each colour channel, as percentages, channels which are `None` are left
+/// as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gains {
+    pub red: Option<u16>,
+    pub green: Option<u16>,
+    pub blue: Option<u16>,
+}
+
+impl Gains {
+    /// The feature and percentage of each channel which is set.
+    pub fn channels(self) -> impl Iterator<Item = (Feature, u16)> {
+        [
+            (Feature::RedGain, self.red),
+            (Feature::GreenGain, self.green),
+            (Feature::BlueGain, self.blue),
+        ]
+        .into_iter()
+        .filter_map(|(feature, percent)| Some((feature, percent?)))
+    }
+}
+
+/// Values set together by `--preset`, features which are `None` are left as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Preset {
+    /// Brightness, as a percentage.
+    pub brightness: Option<u16>,
+    /// Contrast, as a percentage.
+    pub contrast: Option<u16>,
+    /// The raw value of the colour preset to select, see [`parse_color_preset`].
+    pub color_preset: Option<u8>,
+}
+
+impl Preset {
+    // the features set as percentages, then those set to a raw value
+    fn features(
+        self,
+    ) -> (
+        impl Iterator<Item = (Feature, u16)>,
+        impl Iterator<Item = (Feature, u16)>,
+    ) {
+        let percentages = [
+            (Feature::Luminance, self.brightness),
+            (Feature::Contrast, self.contrast),
+        ]
+        .into_iter()
+        .filter_map(|(feature, percent)| Some((feature, percent?)));
+        let values = self
+            .color_preset
+            .map(|preset| (Feature::ColorPreset, u16::from(preset)))
+            .into_iter();
+
+        (percentages, values)
+    }
+}
+
+/// An operation to carry out on each selected display.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Change(Feature, BrightnessChange),
+    SetInput(u16),
+    /// Prints the value of each feature, brightness alone as set by [`Options::format`].
+    Get(GetFeatures),
+    /// Flashes the brightness so the display can be spotted, then restores it.
+    Identify,
+    /// Prints the capabilities string and the features it lists.
+    Capabilities,
+    /// Prints a table of the value and maximum of every feature the capabilities list.
+    Features,
+    /// Prints the raw value and maximum of a feature.
+    VcpGet(u8),
+    Power(PowerState),
+    /// Selects a colour temperature preset, checked against the display's capabilities.
+    ColorPreset(u8),
+    /// Writes a raw value to a feature.
+    VcpSet {
+        code: u8,
+        value: u16,
+    },
+    /// Restores the display's factory defaults.
+    Reset,
+    /// Sets the video gain of some colour channels, one after the other.
+    SetGains(Gains),
+    /// Sets each feature in the preset, one after the other.
+    Preset(Preset),
+    /// Copies the brightness of the display with this index to the others, plus an offset in
+    /// percent.
+    ///
+    /// This spans several displays, so it can't be executed on one. Callers read the source
+    /// with [`read_brightness`] and then run an absolute [`Action::Change`] on the rest.
+    SyncFrom(usize, i16),
+}
+
+impl Action {
+    pub fn is_noop(self) -> bool {
+        match self {
+            Action::Change(_, BrightnessChange::Relative(offset)) => offset == 0,
+            Action::Change(_, BrightnessChange::RelativeFactor(factor)) => factor == 1.0,
+            _ => false,
+        }
+    }
+
+    /// Whether the action only reads from displays.
+    pub fn is_read_only(self) -> bool {
+        matches!(
+            self,
+            Action::Get(_) | Action::Capabilities | Action::Features | Action::VcpGet(_)
+        )
+    }
+
+    /// The feature the action reads or changes, if it acts on a single feature.
+    pub fn feature(self) -> Option<Feature> {
+        Some(match self {
+            Action::Change(feature, _) => feature,
+            Action::SetInput(_) => Feature::InputSource,
+            Action::Get(features) if !features.is_brightness() => return None,
+            Action::Get(_) | Action::Identify | Action::SyncFrom(..) => Feature::Luminance,
+            Action::Power(_) => Feature::PowerMode,
+            Action::ColorPreset(_) => Feature::ColorPreset,
+            Action::Reset => Feature::FactoryReset,
+            Action::VcpGet(code) | Action::VcpSet { code, .. } => Feature::Vcp(code),
+            Action::Capabilities | Action::Features | Action::SetGains(_) | Action::Preset(_) => {
+                return None;
+            }
+        })
+    }
+
+    /// Carries out the action on `display`, returning the percentage the feature is left at
+    /// when it is known, or with [`Options::raw`] the value itself.
+    pub fn execute(
+        self,
+        display: &mut Monitor,
+        display_no: usize,
+        options: Options,
+        out: &mut Output,
+    ) -> Result<Option<u16>, Error> {
+        match self {
+            Action::SetGains(gains) => {
+                let channels = gains.channels();
+                set_features(
+                    display,
+                    display_no,
+                    channels,
+                    [].into_iter(),
+                    "gains",
+                    options,
+                    out,
+                )?;
+                return Ok(None);
+            }
+            Action::Preset(preset) => {
+                let (percentages, values) = preset.features();
+                set_features(
+                    display,
+                    display_no,
+                    percentages,
+                    values,
+                    "preset",
+                    options,
+                    out,
+                )?;
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        let disp = display_name(display_no, display);
+        let Some(feature) = self.feature() else {
+            match self {
+                Action::Features => print_features(display, display_no, options, out)?,
+                Action::Get(features) => print_values(display, display_no, features, options, out)?,
+                _ => print_capabilities(display, display_no, options, out)?,
+            }
+            return Ok(None);
+        };
+
+        // these are written without reading the current value first, each is described in
+        // both the progressive and, for dry runs, the plain form
+        let write = match self {
+            Action::SetInput(input) => Some((
+                input,
+                ("Switching", "switch"),
+                format!("{disp} to input {}", feature.value_name(input)),
+            )),
+            Action::ColorPreset(preset) => {
+                let preset = u16::from(preset);
+                check_supported(display.ddc(feature)?, feature, preset)?;
+                Some((
+                    preset,
+                    ("Setting", "set"),
+                    format!("colour preset of {disp} to {}", feature.value_name(preset)),
+                ))
+            }
+            Action::Power(state) => Some((
+                state.value(),
+                ("Setting", "set"),
+                format!("power of {disp} to {}", state.name()),
+            )),
+            // any non-zero value starts the reset
+            Action::Reset => Some((
+                1,
+                ("Resetting", "reset"),
+                format!("{disp} to factory defaults"),
+            )),
+            Action::VcpSet { code, value } => Some((
+                value,
+                ("Setting", "set"),
+                format!("VCP 0x{code:02X} of {disp} to {value}"),
+            )),
+            _ => None,
+        };
+        if let Some((value, (doing, verb), description)) = write {
+            display.ddc(feature)?;
+            if options.dry_run {
+                if options.json {
+                    let info = Json::Object(vec![
+                        ("display", display_no.into()),
+                        ("model", display.info.model_name.as_deref().into()),
+                        ("code", feature.code().into()),
+                        ("value", value.into()),
+                    ]);
+                    out.println(format_args!("{info}"));
+                } else {
+                    out.println(format_args!("Would {verb} {description}"));
+                }
+                return Ok(None);
+            }
+
+            out.info(format_args!("{doing} {description}"));
+            let result = timing::time(
+                Some(display_no),
+                || operation(&disp, "write", feature),
+                || write_feature(&mut display.backend, feature.code(), value, options.retries),
+            );
+            settle(&mut display.backend, options.delay);
+            let Err(e) = result else {
+                return Ok(None);
+            };
+
+            // writes aren't acknowledged, so DDC/CI is only checked once one has failed
+            return Err(if !probe(display.ddc(feature)?) {
+                Error::DdcUnavailable(e)
+            } else if let Action::Power(state) = self {
+                Error::PowerFailed(state, e)
+            } else {
+                Error::SetFailed(feature, e)
+            });
+        }
+
+        if let Action::VcpGet(code) = self {
+            let handle = display.ddc(feature)?;
+            let vcp = timing::time(
+                Some(display_no),
+                || operation(&disp, "read", feature),
+                || read_vcp(handle, feature, options.retries, options.timeout),
+            )
+            .map_err(no_response_as_unavailable)?;
+            settle(&mut display.backend, options.delay);
+            if options.json {
+                let info = Json::Object(vec![
+                    ("display", display_no.into()),
+                    ("model", display.info.model_name.as_deref().into()),
+                    ("code", code.into()),
+                    ("value", vcp.value().into()),
+                    ("maximum", vcp.maximum().into()),
+                ]);
+                out.println(format_args!("{info}"));
+            } else {
+                out.println(format_args!(
+                    "VCP 0x{code:02X} of {disp} is {} (maximum {})",
+                    vcp.value(),
+                    vcp.maximum()
+                ));
+            }
+            return Ok(None);
+        }
+
+        // the first read doubles as a check that the display speaks DDC/CI at all
+        let (mut old_value, maximum) = timing::time(
+            Some(display_no),
+            || operation(&disp, "read", feature),
+            || {
+                read_feature(
+                    &mut display.backend,
+                    feature,
+                    options.retries,
+                    options.timeout,
+                )
+            },
+        )
+        .map_err(no_response_as_unavailable)?;
+        // commands wait out the delay left by the one before them anyway, so waiting here only
+        // slows down reads which nothing is written after
+        if !matches!(self, Action::Get(_)) && !options.dry_run {
+            settle(&mut display.backend, options.delay);
+        }
+        // `--get --raw` is there to show exactly what the display reports, quirks included
+        let as_read = options.raw && matches!(self, Action::Get(_));
+        if old_value > maximum && !as_read {
+            out.eprintln(format_args!(
+                "{disp} reported a {} of {old_value}, above its maximum of {maximum}, treating it as {maximum}",
+                feature.name()
+            ));
+            old_value = maximum;
+        }
+        let old_percent = to_percent(old_value, maximum);
+        // what is reported back, by default everything is a percentage
+        let (shown, unit) = if options.raw {
+            (old_value, "")
+        } else {
+            (old_percent, "%")
+        };
+
+        match self {
+            Action::Change(_, brightness_change) => {
+                out.before = Some(shown);
+                let range = if options.raw { maximum } else { 100 };
+                if let Some(requested) = brightness_change.requested(shown)
+                    && !(0..=i32::from(range)).contains(&requested)
+                {
+                    out.eprintln(format_args!(
+                        "Requested a {} of {requested}{unit} for {disp}, clamped to {}{unit}",
+                        feature.name(),
+                        requested.clamp(0, i32::from(range))
+                    ));
+                }
+                let mut new_value = if options.raw {
+                    brightness_change.apply_raw(old_value, maximum)
+                } else if options.perceptual {
+                    brightness_change.apply_perceptual(old_value, maximum)
+                } else {
+                    brightness_change.apply(old_value, maximum)
+                };
+                if feature == Feature::Luminance {
+                    new_value = options.limits.clamp(new_value, maximum);
+                }
+                // a display already below its floor is left where it is rather than raised
+                let floor = from_percent(options.limits.floor, maximum).min(old_value);
+                let floored = feature == Feature::Luminance
+                    && brightness_change.is_relative()
+                    && new_value < floor;
+                if floored {
+                    new_value = floor;
+                }
+                if old_value == new_value && floored {
+                    out.info(format_args!(
+                        "No change needed for {disp}, it is at its safe floor of {}%, use --force to go lower",
+                        options.limits.floor
+                    ));
+                    return Ok(Some(shown));
+                }
+                if old_value == new_value {
+                    out.info(format_args!("No change needed for {disp}"));
+                    return Ok(Some(shown));
+                }
+
+                let new_percent = to_percent(new_value, maximum);
+                let new_shown = if options.raw { new_value } else { new_percent };
+                // with --json the dry run is shown by the table of changes instead, so that it
+                // is the only JSON printed
+                if options.dry_run {
+                    if !options.json {
+                        let change = (shown, new_shown);
+                        print_dry_run_change(
+                            display, display_no, feature, change, unit, options, out,
+                        );
+                    }
+                    return Ok(Some(new_shown));
+                }
+                if let Some(confirm) = options.confirm
+                    && feature == Feature::Luminance
+                    && new_percent.abs_diff(old_percent) > confirm.delta
+                    && !confirm_jump(&disp, (shown, new_shown), unit, confirm)
+                {
+                    return Err(Error::Declined(feature));
+                }
+
+                out.info(format_args!(
+                    "Changing {} of {disp} from {shown}{unit} to {new_shown}{unit}",
+                    feature.name(),
+                ));
+                let write = || operation(&disp, "write", feature);
+                let result = timing::time(Some(display_no), write, || match options.fade {
+                    Some(fade_options) => fade(
+                        &mut display.backend,
+                        display_no,
+                        feature.code(),
+                        old_value,
+                        new_value,
+                        fade_options,
+                        options,
+                    ),
+                    None => write_feature(
+                        &mut display.backend,
+                        feature.code(),
+                        new_value,
+                        options.retries,
+                    ),
+                });
+                settle(&mut display.backend, options.delay);
+                result.map_err(|e| Error::SetFailed(feature, e))?;
+
+                // a fade cut short is put back rather than left part of the way there
+                if options.fade.is_some() && signal::interrupted() {
+                    write_feature(
+                        &mut display.backend,
+                        feature.code(),
+                        old_value,
+                        options.retries,
+                    )
+                    .map_err(|e| Error::SetFailed(feature, e))?;
+                    settle(&mut display.backend, options.delay);
+                    return Err(Error::Interrupted(feature));
+                }
+
+                if feature == Feature::Luminance
+                    && let Some(serial) = display.info.serial
+                    && let Err(e) =
+                        undo::record(serial, display.info.model_name.clone(), old_percent)
+                {
+                    out.eprintln(format_args!("Failed to record brightness for --undo: {e}"));
+                }
+                if options.verify {
+                    verify_write(display, &disp, feature, (new_value, maximum), options, out);
+                }
+                return Ok(Some(new_shown));
+            }
+            Action::Identify if options.dry_run => {
+                out.println(format_args!("Would flash {disp}"));
+            }
+            Action::Identify => {
+                out.info(format_args!("Flashing {disp}"));
+                // flash towards whichever end makes the change most visible
+                let flash_percent = if old_percent > 50 { 10 } else { 100 };
+                let flash_value = from_percent(flash_percent, maximum);
+                let result = timing::time(
+                    Some(display_no),
+                    || format!("{disp}: flash"),
+                    || flash(&mut display.backend, old_value, flash_value, options),
+                );
+
+                // the original brightness is restored even if flashing failed or was interrupted
+                let restored = write_feature(
+                    &mut display.backend,
+                    feature.code(),
+                    old_value,
+                    options.retries,
+                );
+                settle(&mut display.backend, options.delay);
+                result
+                    .and(restored)
+                    .map_err(|e| Error::SetFailed(feature, e))?;
+            }
+            Action::Get(_) if options.json => {
+                let mut fields = vec![
+                    ("display", display_no.into()),
+                    ("model", display.info.model_name.as_deref().into()),
+                    ("brightness", shown.into()),
+                ];
+                if options.raw {
+                    fields.push(("maximum", maximum.into()));
+                }
+                out.println(format_args!("{}", Json::Object(fields)));
+            }
+            Action::Get(_) if options.format == GetFormat::Value => {
+                out.println(format_args!("{shown}"));
+            }
+            Action::Get(_) if options.format == GetFormat::Bar => {
+                out.println(format_args!("{}", bar(old_percent)));
+            }
+            Action::Get(_) if options.raw => {
+                out.println(format_args!("{disp}: value={old_value} max={maximum}"));
+            }
+            Action::Get(_) => {
+                out.println(format_args!("Brightness of {disp} is {old_percent}%"));
+            }
+            Action::SetInput(_)
+            | Action::Power(_)
+            | Action::ColorPreset(_)
+            | Action::VcpSet { .. }
+            | Action::VcpGet(_)
+            | Action::SetGains(_)
+            | Action::Preset(_)
+            | Action::Reset
+            | Action::Capabilities
+            | Action::Features => {
+                unreachable!("raw features are handled before reading a percentage")
+            }
+            Action::SyncFrom(..) => unreachable!("syncing is resolved into a change by the caller"),
+        }
+
+        Ok(Some(shown))
+    }
+}
+
+// each feature is changed on its own, so one the display doesn't support doesn't stop the rest,
+// they are all read first so that the writes can go out as one batch
+//
+// `percentages` are read to scale them to the display's maximum, `values` are written as they
+// are once the display's capabilities are checked for them
+fn set_features(
+    display: &mut Monitor,
+    display_no: usize,
+    percentages: impl Iterator<Item = (Feature, u16)>,
+    values: impl Iterator<Item = (Feature, u16)>,
+    what: &str,
+    options: Options,
+    out: &mut Output,
+) -> Result<(), Error> {
+    let disp = display_name(display_no, display);
+    let mut failures = Vec::new();
+    let mut writes = Vec::new();
+    // the features set as percentages, which are read back with --verify
+    let mut verified = Vec::new();
+    // the brightness to record for --undo, if it is changed
+    let mut undo_percent = None;
+    for (feature, percent) in percentages {
+        let read = timing::time(
+            Some(display_no),
+            || operation(&disp, "read", feature),
+            || {
+                read_feature(
+                    &mut display.backend,
+                    feature,
+                    options.retries,
+                    options.timeout,
+                )
+            },
+        );
+        let (old_value, maximum) = match read.map_err(no_response_as_unavailable) {
+            Ok(read) => read,
+            // nothing else is going to get through either
+            Err(e @ Error::DdcUnavailable(_)) => return Err(e),
+            Err(e) => {
+                failures.push(e);
+                continue;
+            }
+        };
+        settle(&mut display.backend, options.delay);
+
+        let mut new_value = from_percent(percent, maximum);
+        if feature == Feature::Luminance {
+            new_value = options.limits.clamp(new_value, maximum);
+        }
+        let (old_percent, new_percent) = (
+            to_percent(old_value, maximum),
+            to_percent(new_value, maximum),
+        );
+        if old_value.min(maximum) == new_value {
+            out.info(format_args!(
+                "No change needed for {} of {disp}",
+                feature.name()
+            ));
+        } else if options.dry_run {
+            print_dry_run_change(
+                display,
+                display_no,
+                feature,
+                (old_percent, new_percent),
+                "%",
+                options,
+                out,
+            );
+        } else {
+            out.info(format_args!(
+                "Changing {} of {disp} from {old_percent}% to {new_percent}%",
+                feature.name(),
+            ));
+            writes.push((feature, new_value));
+            verified.push((feature, new_value, maximum));
+            if feature == Feature::Luminance {
+                undo_percent = Some(old_percent);
+            }
+        }
+    }
+
+    for (feature, value) in values {
+        let checked = display
+            .ddc(feature)
+            .and_then(|handle| check_supported(handle, feature, value));
+        if let Err(e) = checked {
+            failures.push(e);
+            continue;
+        }
+
+        let name = feature.value_name(value);
+        if options.dry_run {
+            out.println(format_args!(
+                "Would set {} of {disp} to {name}",
+                feature.name()
+            ));
+        } else {
+            out.info(format_args!(
+                "Setting {} of {disp} to {name}",
+                feature.name()
+            ));
+            writes.push((feature, value));
+        }
+    }
+
+    let write = || format!("{disp}: write {what}");
+    let write_failures = timing::time(Some(display_no), write, || {
+        write_batch(&mut display.backend, &writes, options)
+    });
+    let brightness_failed = write_failures
+        .iter()
+        .any(|e| matches!(e, Error::SetFailed(Feature::Luminance, _)));
+    if let Some(old_percent) = undo_percent
+        && !brightness_failed
+        && let Some(serial) = display.info.serial
+        && let Err(e) = undo::record(serial, display.info.model_name.clone(), old_percent)
+    {
+        out.eprintln(format_args!("Failed to record brightness for --undo: {e}"));
+    }
+    if options.verify {
+        for (feature, value, maximum) in verified {
+            let failed = write_failures
+                .iter()
+                .any(|e| matches!(e, Error::SetFailed(f, _) if *f == feature));
+            if !failed {
+                verify_write(display, &disp, feature, (value, maximum), options, out);
+            }
+        }
+    }
+    failures.extend(write_failures);
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0)),
+        _ => Err(Error::Channels(failures)),
+    }
+}
+
+// reads a feature back after `value` out of `maximum` was written to it, warning if the display
+// reports something more than a percent away, which --raw shows in the display's own units
+fn verify_write(
+    display: &mut Monitor,
+    disp: &str,
+    feature: Feature,
+    (value, maximum): (u16, u16),
+    options: Options,
+    out: &mut Output,
+) {
+    let read = read_feature(
+        &mut display.backend,
+        feature,
+        options.retries,
+        options.timeout,
+    );
+    let (actual, _) = match read {
+        Ok(read) => read,
+        Err(e) => {
+            out.eprintln(format_args!(
+                "Couldn't read back the {} of {disp} to verify it: {e}",
+                feature.name()
+            ));
+            return;
+        }
+    };
+
+    let (requested_percent, actual_percent) =
+        (to_percent(value, maximum), to_percent(actual, maximum));
+    if requested_percent.abs_diff(actual_percent) <= 1 {
+        return;
+    }
+    let (requested, actual, unit) = if options.raw {
+        (value, actual, "")
+    } else {
+        (requested_percent, actual_percent, "%")
+    };
+    out.eprintln(format_args!(
+        "{disp} reports a {} of {actual}{unit} after being set to {requested}{unit}, it may have ignored or rounded the change",
+        feature.name()
+    ));
+}
+
+// writes distinct features one after the other, returning the error for each feature which
+// failed, settling once at the end rather than after each write
+//
+// the DDC host waits out whatever is left of the display's delay before sending each command,
+// so the writes are still spaced out as DDC/CI requires, but time spent between them counts
+// towards that delay instead of adding to it
+fn write_batch(backend: &mut Backend, writes: &[(Feature, u16)], options: Options) -> Vec<Error> {
+    if writes.is_empty() {
+        return Vec::new();
+    }
+
+    let mut failures = Vec::new();
+    for &(feature, value) in writes {
+        if let (Backend::Ddc(handle), Some(delay)) = (&mut *backend, options.delay) {
+            override_delay(handle, delay);
+        }
+        if let Err(e) = write_feature(backend, feature.code(), value, options.retries) {
+            failures.push(Error::SetFailed(feature, e));
+        }
+    }
+    settle(backend, options.delay);
+
+    failures
+}
+
+// describes a change which --dry-run skipped
+fn print_dry_run_change(
+    display: &Monitor,
+    display_no: usize,
+    feature: Feature,
+    (from, to): (u16, u16),
+    unit: &str,
+    options: Options,
+    out: &mut Output,
+) {
+    if options.json {
+        let info = Json::Object(vec![
+            ("display", display_no.into()),
+            ("model", display.info.model_name.as_deref().into()),
+            ("feature", feature.name().into()),
+            ("from", from.into()),
+            ("to", to.into()),
+        ]);
+        out.println(format_args!("{info}"));
+    } else {
+        out.println(format_args!(
+            "Would change {} of {} from {from}{unit} to {to}{unit}",
+            feature.name(),
+            display_name(display_no, display),
+        ));
+    }
+}
+
+/// How changing a display went, as a row of [`print_change_table`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeRow {
+    pub display: usize,
+    pub model: Option<String>,
+    pub before: Option<u16>,
+    pub after: Option<u16>,
+    /// One of changed, unchanged, dry run or failed.
+    pub status: &'static str,
+}
+
+/// Prints a row per display changed with its value before and after, and whether it changed,
+/// e.g.
+///
+/// ```text
+///   display  model        before  after  status
+///   0        DELL U2720Q  40%     50%    changed
+/// ```
+///
+/// With `--json` the rows are printed as a single array instead, which is all a change prints
+/// on stdout, so [`Action::Change`] leaves its per-display JSON out.
+pub fn print_change_table(rows: &[ChangeRow], options: Options, out: &mut Output) {
+    if options.json {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                Json::Object(vec![
+                    ("display", row.display.into()),
+                    ("model", row.model.as_deref().into()),
+                    ("before", row.before.into()),
+                    ("after", row.after.into()),
+                    ("status", row.status.into()),
+                ])
+            })
+            .collect();
+        out.println(format_args!("{}", Json::Array(rows)));
+        return;
+    }
+
+    let unit = if options.raw { "" } else { "%" };
+    let value =
+        |value: Option<u16>| value.map_or("-".to_string(), |value| format!("{value}{unit}"));
+    let mut lines = vec![[
+        "display".to_string(),
+        "model".to_string(),
+        "before".to_string(),
+        "after".to_string(),
+        "status".to_string(),
+    ]];
+    lines.extend(rows.iter().map(|row| {
+        [
+            row.display.to_string(),
+            row.model.as_deref().unwrap_or("Unknown Model").to_string(),
+            value(row.before),
+            value(row.after),
+            row.status.to_string(),
+        ]
+    }));
+
+    let widths: Vec<_> = (0..5)
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for line in lines {
+        let cells: Vec<_> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        out.info(format_args!("  {}", cells.join("  ").trim_end()));
+    }
+}
+
+// a feature listed in the capabilities, with its name and the values it allows if they are
+// listed too
+struct CapsFeature {
+    code: u8,
+    feature: Feature,
+    name: String,
+    values: Vec<u16>,
+}
+
+// reads and parses the capabilities string, or None if the display isn't controlled over DDC
+fn read_capabilities(
+    display: &mut Monitor,
+    display_no: usize,
+    options: Options,
+) -> Result<Option<(String, Vec<CapsFeature>)>, Error> {
+    let disp = display_name(display_no, display);
+    let Backend::Ddc(handle) = &mut display.backend else {
+        return Ok(None);
+    };
+    let what = || format!("{disp}: read capabilities");
+    let raw = timing::time(Some(display_no), what, || {
+        with_retries(options.retries, handle, |handle| {
+            traced(format_args!("read capabilities"), || {
+                handle.capabilities_string()
+            })
+        })
+    })
+    .map_err(Error::Capabilities)?;
+    settle(&mut display.backend, options.delay);
+    let caps = mccs_caps::parse_capabilities(&raw).map_err(|e| Error::Capabilities(e.into()))?;
+
+    let features = caps
+        .vcp_features
+        .iter()
+        .map(|(&code, descriptor)| {
+            let feature = Feature::from_code(code);
+            let name = match (feature, &descriptor.name) {
+                (_, Some(name)) => name.clone(),
+                (Feature::Vcp(_), None) => String::new(),
+                (feature, None) => feature.name().to_string(),
+            };
+            let values = descriptor.values().map(|&v| u16::from(v)).collect();
+            CapsFeature {
+                code,
+                feature,
+                name,
+                values,
+            }
+        })
+        .collect();
+
+    Ok(Some((String::from_utf8_lossy(&raw).into_owned(), features)))
+}
+
+fn print_capabilities(
+    display: &mut Monitor,
+    display_no: usize,
+    options: Options,
+    out: &mut Output,
+) -> Result<(), Error> {
+    let disp = display_name(display_no, display);
+    let Some((raw_str, features)) = read_capabilities(display, display_no, options)? else {
+        match &display.backend {
+            Backend::Backlight(backlight) => out.println(format_args!(
+                "{disp} is controlled by backlight {}, which only supports brightness",
+                backlight.name()
+            )),
+            backend => out.println(format_args!(
+                "{disp} is controlled by {}, which doesn't report capabilities",
+                backend.name()
+            )),
+        }
+        return Ok(());
+    };
+
+    if options.json {
+        let features = features
+            .into_iter()
+            .map(
+                |CapsFeature {
+                     code,
+                     feature,
+                     name,
+                     values,
+                 }| {
+                    let names = values
+                        .iter()
+                        .map(|&v| feature.value_name(v).into())
+                        .collect();
+                    Json::Object(vec![
+                        ("code", code.into()),
+                        ("name", (!name.is_empty()).then_some(name).into()),
+                        (
+                            "values",
+                            Json::Array(values.into_iter().map(Into::into).collect()),
+                        ),
+                        ("value_names", Json::Array(names)),
+                    ])
+                },
+            )
+            .collect();
+        let info = Json::Object(vec![
+            ("display", display_no.into()),
+            ("model", display.info.model_name.as_deref().into()),
+            ("capabilities", raw_str.as_str().into()),
+            ("features", Json::Array(features)),
+        ]);
+        out.println(format_args!("{info}"));
+        return Ok(());
+    }
+
+    out.println(format_args!("{disp}:"));
+    out.println(format_args!("  capabilities: {raw_str}"));
+    out.println(format_args!("  supported features:"));
+    for CapsFeature {
+        code,
+        feature,
+        name,
+        values,
+    } in features
+    {
+        let mut line = format!("    0x{code:02X}");
+        if !name.is_empty() {
+            line.push_str(&format!(" {name}"));
+        }
+        if !values.is_empty() {
+            let names: Vec<_> = values.iter().map(|&v| feature.value_name(v)).collect();
+            line.push_str(&format!(": {}", names.join(", ")));
+        }
+        out.println(format_args!("{line}"));
+    }
+
+    Ok(())
+}
+
+// reads every feature the capabilities list, or only brightness for displays not controlled
+// over DDC, features which can't be read are shown with the reason rather than failing
+fn print_features(
+    display: &mut Monitor,
+    display_no: usize,
+    options: Options,
+    out: &mut Output,
+) -> Result<(), Error> {
+    let disp = display_name(display_no, display);
+    let features = match read_capabilities(display, display_no, options)? {
+        Some((_, features)) => features,
+        None => vec![CapsFeature {
+            code: Feature::Luminance.code(),
+            feature: Feature::Luminance,
+            name: Feature::Luminance.name().to_string(),
+            values: Vec::new(),
+        }],
+    };
+
+    let mut rows = Vec::new();
+    for CapsFeature {
+        code,
+        feature,
+        name,
+        ..
+    } in features
+    {
+        let read = timing::time(
+            Some(display_no),
+            || operation(&disp, "read", feature),
+            || {
+                read_feature(
+                    &mut display.backend,
+                    feature,
+                    options.retries,
+                    options.timeout,
+                )
+            },
+        );
+        settle(&mut display.backend, options.delay);
+        rows.push((code, name, read));
+    }
+
+    if options.json {
+        let features = rows
+            .into_iter()
+            .map(|(code, name, read)| {
+                let (value, maximum) = read.as_ref().ok().copied().unzip();
+                Json::Object(vec![
+                    ("code", code.into()),
+                    ("name", (!name.is_empty()).then_some(name).into()),
+                    ("value", value.into()),
+                    ("maximum", maximum.into()),
+                    ("error", read.err().map(|e| e.to_string()).into()),
+                ])
+            })
+            .collect();
+        let info = Json::Object(vec![
+            ("display", display_no.into()),
+            ("model", display.info.model_name.as_deref().into()),
+            ("features", Json::Array(features)),
+        ]);
+        out.println(format_args!("{info}"));
+        return Ok(());
+    }
+
+    let name_width = rows
+        .iter()
+        .map(|(_, name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+    out.println(format_args!("{disp}:"));
+    out.println(format_args!(
+        "  CODE  {:name_width$}  {:>5}  {:>7}",
+        "NAME", "VALUE", "MAXIMUM"
+    ));
+    for (code, name, read) in rows {
+        match read {
+            Ok((value, maximum)) => out.println(format_args!(
+                "  0x{code:02X}  {name:name_width$}  {value:>5}  {maximum:>7}"
+            )),
+            Err(e) => out.println(format_args!("  0x{code:02X}  {name:name_width$}  ({e})")),
+        }
+    }
+
+    Ok(())
+}
+
+// reads each feature in turn, those the display doesn't support are shown as missing rather
+// than failing the rest
+fn print_values(
+    display: &mut Monitor,
+    display_no: usize,
+    features: GetFeatures,
+    options: Options,
+    out: &mut Output,
+) -> Result<(), Error> {
+    let disp = display_name(display_no, display);
+    let mut rows = Vec::new();
+    for feature in features.iter() {
+        let read = timing::time(
+            Some(display_no),
+            || operation(&disp, "read", feature),
+            || {
+                read_feature(
+                    &mut display.backend,
+                    feature,
+                    options.retries,
+                    options.timeout,
+                )
+            },
+        )
+        .map_err(no_response_as_unavailable);
+        settle(&mut display.backend, options.delay);
+        // nothing else is going to get through either
+        if let Err(e @ Error::DdcUnavailable(_)) = read {
+            return Err(e);
+        }
+        let (name, _) = GET_FEATURES
+            .iter()
+            .find(|(_, f)| *f == feature)
+            .expect("features to get are parsed from GET_FEATURES");
+        rows.push((
+            *name,
+            read.map(|read| shown_value(feature, read, options.raw)),
+        ));
+    }
+
+    if options.json {
+        let values = rows
+            .into_iter()
+            .map(|(name, shown)| (name, shown.ok().map(|(_, json)| json).into()))
+            .collect();
+        let info = Json::Object(vec![
+            ("display", display_no.into()),
+            ("model", display.info.model_name.as_deref().into()),
+            ("features", Json::Object(values)),
+        ]);
+        out.println(format_args!("{info}"));
+        return Ok(());
+    }
+
+    out.println(format_args!("{disp}:"));
+    for (name, shown) in rows {
+        match shown {
+            Ok((text, _)) => out.println(format_args!("  {name}: {text}")),
+            Err(e) => out.println(format_args!("  {name}: ({e})")),
+        }
+    }
+
+    Ok(())
+}
+
+// a value read by `--get` as text and JSON, features with named values are shown by name and
+// the rest as a percentage, or with `--raw` the value itself
+fn shown_value(feature: Feature, (value, maximum): (u16, u16), raw: bool) -> (String, Json) {
+    match feature {
+        Feature::InputSource | Feature::PowerMode | Feature::ColorPreset => {
+            let name = feature.value_name(value);
+            (name.clone(), name.into())
+        }
+        _ if raw => (format!("{value} (maximum {maximum})"), value.into()),
+        _ => {
+            let percent = to_percent(value.min(maximum), maximum);
+            (format!("{percent}%"), percent.into())
+        }
+    }
+}
+
+/// Describes a display for messages, e.g. `display 0 (DELL U2720Q)`.
+pub fn display_name(display_no: usize, display: &Monitor) -> String {
+    let model = display
+        .info
+        .model_name
+        .as_deref()
+        .unwrap_or("Unknown Model");
+
+    format!("display {display_no} ({model})")
+}
+
+// asks on stderr whether to make a change larger than `confirm.delta`, one display at a time as
+// displays are changed in parallel
+fn confirm_jump(disp: &str, (old, new): (u16, u16), unit: &str, confirm: ConfirmJumps) -> bool {
+    static PROMPT: Mutex<()> = Mutex::new(());
+
+    if !io::stdin().is_terminal() {
+        return confirm.proceed_unattended;
+    }
+
+    let _prompt = PROMPT.lock().unwrap_or_else(|e| e.into_inner());
+    let mut answer = String::new();
+    progress::around(|| {
+        eprint!("Change brightness of {disp} from {old}{unit} to {new}{unit}? [y/N] ");
+        let _ = io::stderr().flush();
+        let _ = io::stdin().read_line(&mut answer);
+    });
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Reads the current brightness of `display` as a percentage.
+///
+/// This doesn't wait for the display to be ready for another command, the next one sent to it
+/// waits first instead.
+pub fn get_brightness(display: &mut Monitor) -> Result<u16, Error> {
+    let (value, maximum) = read_feature(
+        &mut display.backend,
+        Feature::Luminance,
+        DEFAULT_RETRIES,
+        None,
+    )?;
+    Ok(to_percent(value, maximum))
+}
+
+/// Reads the current brightness of `display` as a percentage like [`get_brightness`], retrying
+/// and timing out as `options` asks.
+pub fn read_brightness(display: &mut Monitor, options: Options) -> Result<u16, Error> {
+    let (value, maximum) = read_feature(
+        &mut display.backend,
+        Feature::Luminance,
+        options.retries,
+        options.timeout,
+    )?;
+    Ok(to_percent(value, maximum))
+}
+
+/// Sets the brightness of `display` to `percent`, clamped to 100.
+pub fn set_brightness(display: &mut Monitor, percent: u16) -> Result<(), Error> {
+    let (_, maximum) = read_feature(
+        &mut display.backend,
+        Feature::Luminance,
+        DEFAULT_RETRIES,
+        None,
+    )?;
+    settle(&mut display.backend, None);
+    let value = from_percent(percent.min(100), maximum);
+    let result = write_feature(
+        &mut display.backend,
+        LUMINANCE_FEATURE_CODE,
+        value,
+        DEFAULT_RETRIES,
+    );
+    settle(&mut display.backend, None);
+    result.map_err(|e| Error::SetFailed(Feature::Luminance, e))
+}
+
+/// Switches `display` on, for displays which may be in standby.
+///
+/// Displays not controlled over DDC/CI are left alone, as they have no power states.
+pub fn wake(display: &mut Monitor, options: Options) -> Result<(), Error> {
+    if !matches!(display.backend, Backend::Ddc(_)) {
+        return Ok(());
+    }
+
+    let state = PowerState::On;
+    let result = write_feature(
+        &mut display.backend,
+        POWER_MODE_FEATURE_CODE,
+        state.value(),
+        options.retries,
+    );
+    settle(&mut display.backend, options.delay);
+    result.map_err(|e| Error::PowerFailed(state, e))
+}
+
+/// Whether the display lists `feature` in its capabilities, which tells monitors apart from
+/// devices such as capture cards which are found over DDC/CI too.
+///
+/// Displays which don't report their capabilities are checked by reading the feature instead.
+/// With `use_cache` the features the capabilities list are kept in the display cache, so they
+/// only have to be read once per display.
+pub fn supports(display: &mut Monitor, feature: Feature, use_cache: bool) -> bool {
+    !supported(display, &[feature], use_cache).is_empty()
+}
+
+/// Which of `features` the display supports, in the same order, checked as [`supports`] does
+/// but reading the capabilities at most once.
+pub fn supported(display: &mut Monitor, features: &[Feature], use_cache: bool) -> Vec<Feature> {
+    let Monitor { info, backend } = display;
+    let handle = match backend {
+        Backend::Ddc(handle) => handle,
+        Backend::Backlight(_) => {
+            return features
+                .iter()
+                .copied()
+                .filter(|&feature| feature == Feature::Luminance)
+                .collect();
+        }
+        Backend::Custom(backend) => {
+            return features
+                .iter()
+                .copied()
+                .filter(|&feature| backend.read(feature).is_ok())
+                .collect();
+        }
+    };
+
+    let cached = use_cache.then(|| cache::supported_features(info)).flatten();
+    let codes = cached.or_else(|| {
+        let caps = traced(format_args!("read capabilities"), || handle.capabilities());
+        handle.sleep();
+        let codes: Vec<_> = caps.ok()?.vcp_features.keys().copied().collect();
+        if use_cache && let Err(e) = cache::store_supported_features(info, &codes) {
+            log::debug(format_args!("Failed to write display cache: {e}"));
+        }
+        Some(codes)
+    });
+
+    features
+        .iter()
+        .copied()
+        .filter(|&feature| match &codes {
+            Some(codes) => codes.contains(&feature.code()),
+            None => {
+                let result = read_vcp(handle, feature, 0, None);
+                handle.sleep();
+                result.is_ok()
+            }
+        })
+        .collect()
+}
+
+/// Whether the display answers DDC/CI commands, checked with a single read of its brightness.
+pub fn probe(handle: &mut Handle) -> bool {
+    let result = read_vcp(handle, Feature::Luminance, 0, None);
+    handle.sleep();
+    matches!(result, Ok(_) | Err(Error::UnsupportedFeature(_)))
+}
+
+// displays which never answer usually have DDC/CI turned off, which is worth saying
+fn no_response_as_unavailable(e: Error) -> Error {
+    match e {
+        Error::Timeout(_, e) | Error::Communication(ErrorKind::Nak, e) => Error::DdcUnavailable(e),
+        e => e,
+    }
+}
+
+fn read_vcp(
+    handle: &mut Handle,
+    feature: Feature,
+    retries: u32,
+    timeout: Option<Duration>,
+) -> Result<VcpValue, Error> {
+    with_retries(retries, handle, |handle| {
+        traced(format_args!("get VCP 0x{:02X}", feature.code()), || {
+            get_vcp_feature(handle, feature.code(), timeout)
+        })
+    })
+    .map_err(|e| match ErrorKind::of(&e) {
+        ErrorKind::Unsupported => Error::UnsupportedFeature(feature),
+        ErrorKind::Timeout => Error::Timeout(timeout.unwrap_or(DEFAULT_TIMEOUT), e),
+        kind => Error::Communication(kind, e),
+    })
+}
+
+// the same as `get_vcp_feature`, except that it waits `timeout` for the display to reply
+// instead of the time DDC/CI specifies
+#[cfg(target_os = "linux")]
+fn get_vcp_feature(
+    handle: &mut Handle,
+    code: u8,
+    timeout: Option<Duration>,
+) -> Result<VcpValue, DdcError> {
+    use ddc::{
+        Command, CommandResult, DELAY_COMMAND_FAILED_MS, DdcCommandRaw, DdcCommandRawMarker, Delay,
+        commands::GetVcpFeature,
+    };
+
+    let Some(timeout) = timeout else {
+        return handle.get_vcp_feature(code);
+    };
+    let Handle::I2cDevice(ddc) = handle;
+
+    let command = GetVcpFeature::new(code);
+    let mut data = [0; GetVcpFeature::MAX_LEN];
+    command
+        .encode(&mut data)
+        .map_err(ddc_i2c::Error::<std::io::Error>::from)?;
+    let mut out = [0; VcpValue::MAX_LEN + 3];
+    let result = ddc
+        .execute_raw(&data, &mut out, timeout)
+        .and_then(|reply| VcpValue::decode(reply).map_err(ddc_i2c::Error::from));
+
+    // the delay before the next command is set the same way ddc does for its own commands
+    let delay = match result {
+        Ok(_) => GetVcpFeature::DELAY_COMMAND_MS,
+        Err(_) => DELAY_COMMAND_FAILED_MS,
+    };
+    ddc.set_sleep_delay(Delay::new(Duration::from_millis(delay)));
+
+    Ok(result?)
+}
+
+// other backends don't expose the time they wait for a reply
+#[cfg(not(target_os = "linux"))]
+fn get_vcp_feature(
+    handle: &mut Handle,
+    code: u8,
+    _timeout: Option<Duration>,
+) -> Result<VcpValue, DdcError> {
+    handle.get_vcp_feature(code)
+}
+
+// reads the raw value and maximum of a feature
+fn read_feature(
+    backend: &mut Backend,
+    feature: Feature,
+    retries: u32,
+    timeout: Option<Duration>,
+) -> Result<(u16, u16), Error> {
+    let handle = match backend {
+        Backend::Ddc(handle) => handle,
+        Backend::Backlight(backlight) if feature == Feature::Luminance => {
+            return traced(format_args!("read backlight {}", backlight.name()), || {
+                backlight.read()
+            })
+            .map_err(Error::Backlight);
+        }
+        Backend::Backlight(_) => return Err(Error::UnsupportedFeature(feature)),
+        Backend::Custom(backend) => {
+            return traced(format_args!("read {}", feature.name()), || {
+                backend.read(feature)
+            });
+        }
+    };
+    let vcp = read_vcp(handle, feature, retries, timeout)?;
+    // a display reporting a maximum of zero can't be scaled against, assume a percentage
+    let maximum = match vcp.maximum() {
+        0 => 100,
+        maximum => maximum,
+    };
+
+    Ok((vcp.value(), maximum))
+}
+
+// backlights only ever see brightness here, as callers either read the feature first or check
+// it with `Monitor::ddc`
+fn write_feature(
+    backend: &mut Backend,
+    code: u8,
+    value: u16,
+    retries: u32,
+) -> Result<(), DdcError> {
+    match backend {
+        Backend::Ddc(handle) => with_retries(retries, handle, |handle| {
+            traced(format_args!("set VCP 0x{code:02X} to {value}"), || {
+                handle.set_vcp_feature(code, value)
+            })
+        }),
+        Backend::Backlight(backlight) => {
+            debug_assert_eq!(code, LUMINANCE_FEATURE_CODE);
+            traced(
+                format_args!("write {value} to backlight {}", backlight.name()),
+                || backlight.write(value),
+            )
+            .map_err(Into::into)
+        }
+        Backend::Custom(backend) => traced(format_args!("set VCP 0x{code:02X} to {value}"), || {
+            backend.write(code, value)
+        }),
+    }
+}
+
+/// Parses an input source name such as `hdmi1`, or a raw MCCS input source value.
+pub fn parse_input_source(s: &str) -> Result<u16, String> {
+    let name = s.to_ascii_lowercase().replace(['-', '_'], "");
+    if let Some(&(_, value)) = INPUT_SOURCES.iter().find(|(n, _)| *n == name) {
+        return Ok(value);
+    }
+
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| {
+        let names: Vec<_> = INPUT_SOURCES.iter().map(|(n, _)| *n).collect();
+        format!(
+            "unknown input source '{s}', expected a number or one of: {}",
+            names.join(", ")
+        )
+    })
+}
+
+/// Parses a colour preset name such as `6500k` or `srgb`, or a raw MCCS value in hex.
+pub fn parse_color_preset(s: &str) -> Result<u8, String> {
+    let name = s.to_ascii_lowercase().replace(['-', '_'], "");
+    let find = |name: &str| COLOR_PRESETS.iter().find(|(n, _)| *n == name);
+    if let Some(&(_, value)) = find(&name).or_else(|| find(&format!("{name}k"))) {
+        return Ok(value as u8);
+    }
+
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| {
+            let names: Vec<_> = COLOR_PRESETS.iter().map(|(n, _)| *n).collect();
+            format!(
+                "unknown colour preset '{s}', expected a hex value or one of: {}",
+                names.join(", ")
+            )
+        })
+}
+
+// checks the display lists the value as supported in its capabilities, displays which don't
+// report their capabilities are given the benefit of the doubt unless the bus is unusable
+fn check_supported(handle: &mut Handle, feature: Feature, value: u16) -> Result<(), Error> {
+    let caps = match traced(format_args!("read capabilities"), || handle.capabilities()) {
+        Ok(caps) => caps,
+        Err(e) if ErrorKind::of(&e) == ErrorKind::Bus => {
+            return Err(Error::Communication(ErrorKind::Bus, e));
+        }
+        Err(_) => return Ok(()),
+    };
+
+    let Some(descriptor) = caps.vcp_features.get(&feature.code()) else {
+        return Err(Error::UnsupportedFeature(feature));
+    };
+    let supported: Vec<u16> = descriptor.values().map(|&v| u16::from(v)).collect();
+    if supported.is_empty() || supported.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedValue {
+            feature,
+            value,
+            supported,
+        })
+    }
+}
+
+fn flash(
+    backend: &mut Backend,
+    value: u16,
+    flash_value: u16,
+    options: Options,
+) -> Result<(), DdcError> {
+    for _ in 0..IDENTIFY_FLASHES {
+        for value in [flash_value, value] {
+            if signal::interrupted() {
+                return Ok(());
+            }
+            write_feature(backend, LUMINANCE_FEATURE_CODE, value, options.retries)?;
+            settle(backend, options.delay);
+            thread::sleep(IDENTIFY_FLASH_DURATION);
+        }
+    }
+
+    Ok(())
+}
+
+fn fade(
+    backend: &mut Backend,
+    display_no: usize,
+    code: u8,
+    from: u16,
+    to: u16,
+    fade: Fade,
+    options: Options,
+) -> Result<(), DdcError> {
+    // as many steps as a linear fade one unit at a time, easing changes how far each one goes,
+    // so steps which round to the same value are waited out without writing
+    let steps = u32::from(from.abs_diff(to));
+    let step_duration = fade.duration / steps;
+    let mut value = from;
+    for step in 1..=steps {
+        // stops early once interrupted, leaving the caller to restore the original value
+        if signal::interrupted() {
+            break;
+        }
+        let start = Instant::now();
+        let done = fade.easing.progress(step as f32 / steps as f32);
+        let next = (f32::from(from) + (f32::from(to) - f32::from(from)) * done).round() as u16;
+        if next != value {
+            value = next;
+            write_feature(backend, code, value, options.retries)?;
+            settle(backend, options.delay);
+            progress::fade(display_no, value, to);
+        }
+        if let Some(remaining) = step_duration.checked_sub(start.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+
+    Ok(())
+}
+
+// waits until the display is ready for another command, DDC/CI specifies how long that is but
+// many displays cope with far less so `delay` can replace it, failures from too short a delay
+// are left to the retry backoff, backlights are ready straight away
+fn settle(backend: &mut Backend, delay: Option<Duration>) {
+    let Backend::Ddc(handle) = backend else {
+        return;
+    };
+    if let Some(delay) = delay {
+        override_delay(handle, delay);
+    }
+    handle.sleep();
+}
+
+#[cfg(target_os = "linux")]
+fn override_delay(handle: &mut Handle, delay: Duration) {
+    use ddc::{DdcCommandRawMarker, Delay};
+
+    let Handle::I2cDevice(ddc) = handle;
+    ddc.set_sleep_delay(Delay::new(delay));
+}
+
+// other backends don't expose their delay, so they always wait the full time
+#[cfg(not(target_os = "linux"))]
+fn override_delay(_handle: &mut Handle, _delay: Duration) {}
+
+// retries transient failures, doubling the time waited between each attempt, anything else is
+// going to fail the same way again
+fn with_retries<T>(
+    retries: u32,
+    handle: &mut Handle,
+    mut op: impl FnMut(&mut Handle) -> Result<T, DdcError>,
+) -> Result<T, DdcError> {
+    let mut backoff = RETRY_BACKOFF;
+    for _ in 0..retries {
+        match op(handle) {
+            Err(e) if ErrorKind::of(&e).is_transient() => {
+                log::debug(format_args!("command failed: {e}, retrying in {backoff:?}"));
+                handle.sleep();
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+
+    op(handle)
+}
+
+// describes an operation for --timing, e.g. `display 0 (DELL U2720Q): read brightness`
+fn operation(disp: &str, op: &str, feature: Feature) -> String {
+    match feature {
+        Feature::Vcp(code) => format!("{disp}: {op} VCP 0x{code:02X}"),
+        feature => format!("{disp}: {op} {}", feature.name()),
+    }
+}
+
+// runs a single command, logging it along with how long it took
+fn traced<T, E: fmt::Display>(
+    command: fmt::Arguments,
+    op: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    if !log::enabled(log::Level::Trace) {
+        return op();
+    }
+
+    let start = Instant::now();
+    let result = op();
+    match &result {
+        Ok(_) => log::trace(format_args!("{command}: done in {:?}", start.elapsed())),
+        Err(e) => log::trace(format_args!(
+            "{command}: failed after {:?}: {e}",
+            start.elapsed()
+        )),
+    }
+
+    result
+}
+
+/// A change to a percentage value, either by an offset or to an absolute value.
+#[derive(Clone, Copy)]
+pub enum BrightnessChange {
+    Relative(i16),
+    Absolute(u16),
+    /// Scales the current value, e.g. `1.1` for a 10% increase.
+    RelativeFactor(f32),
+    /// Switches to whichever of the two levels the current value is further from.
+    Toggle {
+        low: u16,
+        high: u16,
+    },
+}
+
+impl BrightnessChange {
+    // whether the change is worked out from the current value, rather than setting a new one
+    fn is_relative(self) -> bool {
+        matches!(self, Self::Relative(_) | Self::RelativeFactor(_))
+    }
+
+    /// Applies the change to a raw `value` out of `maximum`, returning the new raw value.
+    pub fn apply(self, value: u16, maximum: u16) -> u16 {
+        // scaling works on the raw value so that small values still move by at least one unit
+        if let Self::RelativeFactor(factor) = self {
+            let scaled = (f32::from(value) * factor)
+                .round()
+                .clamp(0.0, f32::from(maximum)) as u16;
+            return if scaled != value {
+                scaled
+            } else if factor > 1.0 {
+                value.saturating_add(1).min(maximum)
+            } else if factor < 1.0 {
+                value.saturating_sub(1)
+            } else {
+                value
+            };
+        }
+
+        let percent = to_percent(value, maximum);
+        let new_percent = match self {
+            Self::Relative(offset) => {
+                let default = if offset < 0 { 0 } else { 100 };
+                percent.checked_add_signed(offset).unwrap_or(default)
+            }
+            Self::Absolute(value) => value,
+            Self::RelativeFactor(_) => unreachable!("factors are applied to the raw value"),
+            Self::Toggle { low, high } => {
+                if percent.abs_diff(low) <= percent.abs_diff(high) {
+                    high
+                } else {
+                    low
+                }
+            }
+        }
+        .clamp(0, 100);
+
+        from_percent(new_percent, maximum)
+    }
+
+    /// Applies the change like [`BrightnessChange::apply`], except that offsets are steps along
+    /// a gamma curve rather than in percent, so that each step looks about as big as the last
+    /// however bright the display is.
+    pub fn apply_perceptual(self, value: u16, maximum: u16) -> u16 {
+        let Self::Relative(offset) = self else {
+            return self.apply(value, maximum);
+        };
+
+        let percent = to_percent(value, maximum);
+        let perceived = (f32::from(percent) / 100.0).powf(PERCEPTUAL_GAMMA.recip()) * 100.0;
+        let target = ((perceived + f32::from(offset)).clamp(0.0, 100.0) / 100.0)
+            .powf(PERCEPTUAL_GAMMA)
+            * 100.0;
+        let mut new_percent = target.round() as u16;
+        // steps at the dark end can round back to where they started
+        if new_percent == percent && offset != 0 {
+            new_percent = percent.saturating_add_signed(offset.signum()).min(100);
+        }
+
+        from_percent(new_percent, maximum)
+    }
+
+    /// The value an offset or absolute change asks for from `current`, before it is clamped, in
+    /// the same units as both.
+    pub fn requested(self, current: u16) -> Option<i32> {
+        match self {
+            Self::Relative(offset) => Some(i32::from(current) + i32::from(offset)),
+            Self::Absolute(value) => Some(i32::from(value)),
+            Self::RelativeFactor(_) | Self::Toggle { .. } => None,
+        }
+    }
+
+    /// Checks that a change to `feature` given in percent is in range, rejecting percentages
+    /// past 100 rather than clamping them, as they are most likely a mistake.
+    pub fn check_range(self, feature: Feature) -> Result<(), String> {
+        match self {
+            Self::Absolute(value) if value > 100 => Err(format!(
+                "invalid {} {value}, expected 0 to 100, or a raw value with --raw",
+                feature.name()
+            )),
+            Self::Relative(offset) if offset.unsigned_abs() > 100 => Err(format!(
+                "invalid {} change {offset}, expected at most 100 either way",
+                feature.name()
+            )),
+            // also catches a factor of nan, which every comparison fails
+            Self::RelativeFactor(factor) if !(0.0..=2.0).contains(&factor) => Err(format!(
+                "invalid {} change {}%, expected at most 100% either way",
+                feature.name(),
+                (factor - 1.0) * 100.0
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the change treating its values as raw values out of `maximum` rather than
+    /// percentages, for `--raw`.
+    pub fn apply_raw(self, value: u16, maximum: u16) -> u16 {
+        match self {
+            Self::Relative(offset) => value.saturating_add_signed(offset),
+            Self::Absolute(value) => value,
+            Self::RelativeFactor(_) => self.apply(value, maximum),
+            Self::Toggle { low, high } => {
+                if value.abs_diff(low) <= value.abs_diff(high) {
+                    high
+                } else {
+                    low
+                }
+            }
+        }
+        .min(maximum)
+    }
+}
+
+// values beyond the maximum are out of spec, but some displays report them anyway
+fn to_percent(value: u16, maximum: u16) -> u16 {
+    let value = u32::from(value.min(maximum));
+    let maximum = u32::from(maximum);
+    ((value * 100 + maximum / 2) / maximum) as u16
+}
+
+fn from_percent(percent: u16, maximum: u16) -> u16 {
+    ((u32::from(percent) * u32::from(maximum) + 50) / 100) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::MockBackend;
+
+    fn options() -> Options {
+        Options {
+            json: false,
+            fade: None,
+            retries: 0,
+            delay: None,
+            timeout: None,
+            color: false,
+            dry_run: false,
+            limits: Limits::default(),
+            raw: false,
+            perceptual: false,
+            format: GetFormat::Text,
+            confirm: None,
+            verify: false,
+        }
+    }
+
+    fn change(mock: &MockBackend, change: BrightnessChange, options: Options) -> Option<u16> {
+        let action = Action::Change(Feature::Luminance, change);
+        action
+            .execute(&mut mock.monitor(), 0, options, &mut Output::default())
+            .unwrap()
+    }
+
+    fn luminance(value: u16, maximum: u16) -> MockBackend {
+        MockBackend::default().with_feature(LUMINANCE_FEATURE_CODE, value, maximum)
+    }
+
+    #[test]
+    fn get_scales_to_percent() {
+        let mock = luminance(51, 255);
+        let action = Action::Get(GetFeatures::default());
+        let read = action.execute(&mut mock.monitor(), 0, options(), &mut Output::default());
+        assert_eq!(read.unwrap(), Some(20));
+    }
+
+    #[test]
+    fn get_shows_unsupported_features_as_null() {
+        let mock = luminance(51, 255).with_feature(INPUT_SOURCE_FEATURE_CODE, 0x11, 0x1B);
+        let mut out = Output::default();
+        let options = Options {
+            json: true,
+            ..options()
+        };
+        let action = Action::Get(GetFeatures::parse("brightness,contrast,input").unwrap());
+        action
+            .execute(&mut mock.monitor(), 0, options, &mut out)
+            .unwrap();
+        assert_eq!(
+            out.lines[0].1,
+            r#"{"display":0,"model":null,"features":{"brightness":20,"contrast":null,"input":"hdmi1"}}"#
+        );
+    }
+
+    #[test]
+    fn features_without_capabilities_show_brightness() {
+        let mock = luminance(51, 255);
+        let mut out = Output::default();
+        let options = Options {
+            json: true,
+            ..options()
+        };
+        Action::Features
+            .execute(&mut mock.monitor(), 0, options, &mut out)
+            .unwrap();
+        assert_eq!(
+            out.lines[0].1,
+            r#"{"display":0,"model":null,"features":[{"code":16,"name":"brightness","value":51,"maximum":255,"error":null}]}"#
+        );
+    }
+
+    #[test]
+    fn bar_fills_in_eighths() {
+        assert_eq!(bar(0), "          ");
+        assert_eq!(bar(55), "█████▌    ");
+        assert_eq!(bar(100), "██████████");
+    }
+
+    #[test]
+    fn set_scales_from_percent() {
+        let mock = luminance(0, 255);
+        assert_eq!(
+            change(&mock, BrightnessChange::Absolute(50), options()),
+            Some(50)
+        );
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(128));
+    }
+
+    #[test]
+    fn relative_changes_saturate() {
+        let mock = luminance(80, 100);
+        change(&mock, BrightnessChange::Relative(50), options());
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(100));
+
+        change(&mock, BrightnessChange::Relative(-150), options());
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(0));
+    }
+
+    #[test]
+    fn requests_out_of_range_are_clamped() {
+        assert_eq!(BrightnessChange::Absolute(150).requested(40), Some(150));
+        assert_eq!(BrightnessChange::Relative(-50).requested(40), Some(-10));
+        assert_eq!(
+            BrightnessChange::Toggle { low: 0, high: 1 }.requested(40),
+            None
+        );
+
+        let mock = luminance(100, 100);
+        assert_eq!(
+            change(&mock, BrightnessChange::Absolute(150), options()),
+            Some(100)
+        );
+    }
+
+    #[test]
+    fn changes_are_clamped_to_limits() {
+        let mock = luminance(50, 100);
+        let options = Options {
+            limits: Limits {
+                min: 20,
+                max: 80,
+                ..Limits::default()
+            },
+            ..options()
+        };
+        change(&mock, BrightnessChange::Absolute(100), options);
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(80));
+
+        change(&mock, BrightnessChange::Absolute(0), options);
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(20));
+    }
+
+    #[test]
+    fn toggle_switches_to_the_further_level() {
+        let mock = luminance(30, 100);
+        let toggle = BrightnessChange::Toggle { low: 20, high: 90 };
+        change(&mock, toggle, options());
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(90));
+
+        change(&mock, toggle, options());
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(20));
+    }
+
+    #[test]
+    fn easings_start_and_end_at_the_values() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.progress(0.0), 0.0);
+            assert_eq!(easing.progress(1.0), 1.0);
+        }
+        assert!(Easing::EaseIn.progress(0.25) < 0.25);
+        assert!(Easing::EaseOut.progress(0.25) > 0.25);
+        assert_eq!(Easing::EaseInOut.progress(0.5), 0.5);
+    }
+
+    #[test]
+    fn fades_reach_the_new_value() {
+        let mock = luminance(10, 100);
+        let options = Options {
+            fade: Some(Fade {
+                duration: Duration::ZERO,
+                easing: Easing::EaseInOut,
+            }),
+            ..options()
+        };
+        change(&mock, BrightnessChange::Absolute(30), options);
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(30));
+    }
+
+    #[test]
+    fn factors_move_small_values() {
+        assert_eq!(BrightnessChange::RelativeFactor(1.1).apply(3, 100), 4);
+        assert_eq!(BrightnessChange::RelativeFactor(0.9).apply(3, 100), 2);
+        assert_eq!(BrightnessChange::RelativeFactor(2.0).apply(80, 100), 100);
+    }
+
+    #[test]
+    fn factors_past_100_percent_are_rejected() {
+        let check =
+            |factor| BrightnessChange::RelativeFactor(factor).check_range(Feature::Luminance);
+        assert!(check(0.0).is_ok());
+        assert!(check(2.0).is_ok());
+        assert!(check(1.0 - 150.0 / 100.0).is_err());
+        assert!(check(1.0 + 101.0 / 100.0).is_err());
+        assert!(check(f32::NAN).is_err());
+        assert!(check(f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn perceptual_steps_grow_with_brightness() {
+        let step = |percent| BrightnessChange::Relative(5).apply_perceptual(percent, 100) - percent;
+        assert!(step(10) < step(50));
+        assert!(step(50) < step(90));
+        assert_eq!(BrightnessChange::Relative(5).apply_perceptual(0, 100), 1);
+        assert_eq!(BrightnessChange::Relative(-5).apply_perceptual(0, 100), 0);
+        assert_eq!(
+            BrightnessChange::Relative(50).apply_perceptual(90, 100),
+            100
+        );
+    }
+
+    #[test]
+    fn raw_changes_use_device_units() {
+        let mock = luminance(100, 255);
+        let options = Options {
+            raw: true,
+            ..options()
+        };
+        assert_eq!(
+            change(&mock, BrightnessChange::Relative(1), options),
+            Some(101)
+        );
+        assert_eq!(
+            change(&mock, BrightnessChange::Absolute(300), options),
+            Some(255)
+        );
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(255));
+    }
+
+    #[test]
+    fn raw_get_shows_the_value_as_read() {
+        let mock = luminance(120, 100);
+        let mut out = Output::default();
+        let options = Options {
+            raw: true,
+            ..options()
+        };
+        let action = Action::Get(GetFeatures::default());
+        let read = action.execute(&mut mock.monitor(), 0, options, &mut out);
+        assert_eq!(read.unwrap(), Some(120));
+        assert_eq!(
+            out.lines[0].1,
+            "display 0 (Unknown Model): value=120 max=100"
+        );
+    }
+
+    #[test]
+    fn decreases_stop_at_the_safe_floor() {
+        let mock = luminance(30, 100);
+        let options = Options {
+            limits: Limits {
+                floor: 20,
+                ..Limits::default()
+            },
+            ..options()
+        };
+        assert_eq!(
+            change(&mock, BrightnessChange::Relative(-50), options),
+            Some(20)
+        );
+        assert_eq!(
+            change(&mock, BrightnessChange::Relative(-10), options),
+            Some(20)
+        );
+        assert_eq!(
+            change(&mock, BrightnessChange::Absolute(5), options),
+            Some(5)
+        );
+        // below the floor decreases change nothing, rather than raising it to the floor
+        assert_eq!(
+            change(&mock, BrightnessChange::Relative(-10), options),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn values_above_the_maximum_are_treated_as_it() {
+        let mock = luminance(120, 100);
+        change(&mock, BrightnessChange::Relative(-10), options());
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(90));
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let mock = luminance(10, 100);
+        let options = Options {
+            dry_run: true,
+            ..options()
+        };
+        assert_eq!(
+            change(&mock, BrightnessChange::Absolute(60), options),
+            Some(60)
+        );
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(10));
+    }
+
+    #[test]
+    fn presets_set_each_feature() {
+        let mock = luminance(10, 100).with_feature(CONTRAST_FEATURE_CODE, 50, 200);
+        let action = Action::Preset(Preset {
+            brightness: Some(40),
+            contrast: Some(75),
+            color_preset: None,
+        });
+        action
+            .execute(&mut mock.monitor(), 0, options(), &mut Output::default())
+            .unwrap();
+        assert_eq!(mock.value(LUMINANCE_FEATURE_CODE), Some(40));
+        assert_eq!(mock.value(CONTRAST_FEATURE_CODE), Some(150));
+    }
+
+    #[test]
+    fn unsupported_features_are_reported() {
+        let mock = MockBackend::default();
+        let action = Action::Change(Feature::Contrast, BrightnessChange::Relative(10));
+        let result = action.execute(&mut mock.monitor(), 0, options(), &mut Output::default());
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedFeature(Feature::Contrast))
+        ));
+    }
+
+    #[test]
+    fn supported_keeps_the_order_asked_for() {
+        let mock = luminance(50, 100).with_feature(VOLUME_FEATURE_CODE, 10, 100);
+        let features = [Feature::Volume, Feature::Contrast, Feature::Luminance];
+        assert_eq!(
+            supported(&mut mock.monitor(), &features, false),
+            [Feature::Volume, Feature::Luminance]
+        );
+    }
+
+    // skips over the JSON value `s` starts with, returning what follows it, or None if it
+    // doesn't start with one
+    fn skip_json(s: &str) -> Option<&str> {
+        let s = s.trim_start();
+        match s.chars().next()? {
+            open @ ('{' | '[') => {
+                let close = if open == '{' { '}' } else { ']' };
+                let mut rest = s[1..].trim_start();
+                if let Some(rest) = rest.strip_prefix(close) {
+                    return Some(rest);
+                }
+                loop {
+                    if open == '{' {
+                        rest = rest.strip_prefix('"').map(|_| skip_json(rest))??;
+                        rest = rest.trim_start().strip_prefix(':')?;
+                    }
+                    rest = skip_json(rest)?.trim_start();
+                    match rest.strip_prefix(',') {
+                        Some(next) => rest = next.trim_start(),
+                        None => return rest.strip_prefix(close),
+                    }
+                }
+            }
+            '"' => {
+                let mut escaped = false;
+                for (i, c) in s.char_indices().skip(1) {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => return Some(&s[i + 1..]),
+                        _ => {}
+                    }
+                }
+                None
+            }
+            _ => {
+                let end = s
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                    .unwrap_or(s.len());
+                let token = &s[..end];
+                let valid =
+                    matches!(token, "true" | "false" | "null") || token.parse::<f64>().is_ok();
+                valid.then_some(&s[end..])
+            }
+        }
+    }
+
+    #[test]
+    fn json_changes_print_a_single_value() {
+        for dry_run in [false, true] {
+            let options = Options {
+                json: true,
+                dry_run,
+                ..options()
+            };
+            let action = Action::Change(Feature::Luminance, BrightnessChange::Relative(10));
+            let mut outputs = Vec::new();
+            let mut rows = Vec::new();
+            for (i, mock) in [luminance(40, 100), luminance(70, 100)].iter().enumerate() {
+                let mut out = Output::new(options);
+                let after = action.execute(&mut mock.monitor(), i, options, &mut out);
+                rows.push(ChangeRow {
+                    display: i,
+                    model: None,
+                    before: out.before(),
+                    after: after.unwrap(),
+                    status: if dry_run { "dry run" } else { "changed" },
+                });
+                outputs.push(out);
+            }
+            // the table is printed after every display's output, as the binary does
+            let mut out = Output::new(options);
+            print_change_table(&rows, options, &mut out);
+            outputs.push(out);
+
+            let stdout: Vec<_> = outputs
+                .into_iter()
+                .flat_map(|out| out.lines)
+                .filter(|(stream, _)| matches!(stream, Stream::Stdout))
+                .map(|(_, line)| line)
+                .collect();
+            let stdout = stdout.join("\n");
+            assert!(
+                skip_json(&stdout).is_some_and(|rest| rest.trim().is_empty()),
+                "not a single JSON value: {stdout}"
+            );
+            assert!(stdout.contains(r#""before":40,"after":50"#));
+        }
+    }
+}
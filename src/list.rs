@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use ddc_hi::Display;
+
+use crate::{
+    Backend, Error, Feature, Monitor, cache, date::ManufactureDate, dedupe, i2c, json::Json,
+    read_feature, to_percent,
+};
+
+/// What `--list` shows about a display, without anything tied to how the display is reached,
+/// for frontends which let the user pick one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayInfo {
+    /// The display's number, as given to `--display`.
+    pub index: usize,
+    pub model: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_id: Option<u16>,
+    pub serial: Option<u32>,
+    pub manufactured: Option<ManufactureDate>,
+    /// The `/dev/i2c-N` node the display was found on, for displays controlled over DDC/CI.
+    pub bus: Option<PathBuf>,
+    /// How the display is controlled, see [`Backend::name`].
+    pub backend: &'static str,
+    /// Whether the display answers DDC/CI commands, always false for other backends.
+    pub ddc: bool,
+    /// Whether the display's brightness can be read, and so changed.
+    pub supports_brightness: bool,
+    /// The brightness read to see whether the display answers, as a percentage.
+    pub brightness: Option<u16>,
+}
+
+impl DisplayInfo {
+    /// Describes `display`, reading its brightness once to see whether it answers.
+    ///
+    /// Reading the brightness is much quicker than reading the display's capabilities, and
+    /// also catches displays which list brightness but have DDC/CI turned off.
+    pub fn read(index: usize, display: &mut Monitor) -> Self {
+        let read = read_feature(&mut display.backend, Feature::Luminance, 0, None);
+        let ddc = matches!(display.backend, Backend::Ddc(_))
+            && matches!(read, Ok(_) | Err(Error::UnsupportedFeature(_)));
+
+        let info = &display.info;
+        Self {
+            index,
+            model: info.model_name.clone(),
+            manufacturer: info.manufacturer_id.clone(),
+            model_id: info.model_id,
+            serial: info.serial,
+            manufactured: ManufactureDate::of(info),
+            bus: i2c::bus_path(info),
+            backend: display.backend.name(),
+            ddc,
+            supports_brightness: read.is_ok(),
+            brightness: read.ok().map(|(value, maximum)| to_percent(value, maximum)),
+        }
+    }
+
+    /// The display as `--list --json` prints it.
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("index", self.index.into()),
+            ("model", self.model.as_deref().into()),
+            ("manufacturer", self.manufacturer.as_deref().into()),
+            (
+                "model_id",
+                self.model_id.map(|num| format!("{num:04X}")).into(),
+            ),
+            ("serial", self.serial.map(|num| format!("{num:08X}")).into()),
+            (
+                "manufacture_week",
+                self.manufactured.and_then(ManufactureDate::week).into(),
+            ),
+            (
+                "manufacture_year",
+                self.manufactured
+                    .filter(|date| !date.is_model_year())
+                    .map(ManufactureDate::year)
+                    .into(),
+            ),
+            (
+                "model_year",
+                self.manufactured
+                    .filter(|date| date.is_model_year())
+                    .map(ManufactureDate::year)
+                    .into(),
+            ),
+            ("backend", self.backend.into()),
+            (
+                "bus",
+                self.bus
+                    .as_ref()
+                    .map(|bus| bus.to_string_lossy().into_owned())
+                    .into(),
+            ),
+            ("ddc", self.ddc.into()),
+            ("supports_brightness", self.supports_brightness.into()),
+        ])
+    }
+}
+
+/// Finds the connected displays and describes each, numbered as `--display` numbers them.
+///
+/// Displays are taken from the display cache when there is one, otherwise they are enumerated
+/// and the cache is written for next time.
+pub fn list_displays() -> Vec<DisplayInfo> {
+    let displays = cache::load().unwrap_or_else(|| {
+        let displays = Display::enumerate();
+        // failing to write the cache only makes the next call slower
+        let _ = cache::store(&displays);
+        displays
+    });
+
+    Monitor::from_displays(dedupe(displays))
+        .iter_mut()
+        .enumerate()
+        .map(|(i, display)| DisplayInfo::read(i, display))
+        .collect()
+}
@@ -0,0 +1,57 @@
+use std::{fs::File, io, path::PathBuf};
+
+use crate::paths;
+
+const LOCK_NAME: &str = "lock";
+
+/// Held while changing displays, so that concurrent invocations don't interleave their DDC
+/// commands. Released when dropped.
+pub struct Lock {
+    _file: File,
+}
+
+pub fn path() -> Option<PathBuf> {
+    paths::runtime_dir().map(|dir| dir.join(LOCK_NAME))
+}
+
+/// Takes the lock, waiting for whoever holds it unless `wait` is false, in which case an error
+/// of kind [`io::ErrorKind::WouldBlock`] is returned. `on_wait` is called before waiting.
+///
+/// Returns None without `$XDG_RUNTIME_DIR`, as there is nowhere to share the lock.
+#[cfg(unix)]
+pub fn acquire(wait: bool, on_wait: impl FnOnce()) -> io::Result<Option<Lock>> {
+    use std::os::fd::AsRawFd;
+
+    let Some(path) = path() else {
+        return Ok(None);
+    };
+    std::fs::create_dir_all(path.parent().expect("lock path has a parent"))?;
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+
+    let flock = |operation| {
+        // SAFETY: flock only takes the file descriptor, which is kept open by `file`
+        match unsafe { libc::flock(file.as_raw_fd(), operation) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    };
+    match flock(libc::LOCK_EX | libc::LOCK_NB) {
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock && wait => {
+            on_wait();
+            flock(libc::LOCK_EX)?;
+        }
+        result => result?,
+    }
+
+    Ok(Some(Lock { _file: file }))
+}
+
+/// Without flock there is no lock, so this always returns None.
+#[cfg(not(unix))]
+pub fn acquire(_wait: bool, _on_wait: impl FnOnce()) -> io::Result<Option<Lock>> {
+    Ok(None)
+}
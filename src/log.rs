@@ -0,0 +1,59 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::progress;
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// How much is printed besides errors and the output asked for, such as `--get`'s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Quiet,
+    /// Progress messages such as which displays are being changed, the default.
+    Info,
+    /// Also how displays were found and any retried commands.
+    Debug,
+    /// Also every DDC command and how long it took.
+    Trace,
+}
+
+impl Level {
+    /// The level one step more verbose, if there is one.
+    pub fn more(self) -> Self {
+        match self {
+            Self::Quiet => Self::Info,
+            Self::Info => Self::Debug,
+            Self::Debug | Self::Trace => Self::Trace,
+        }
+    }
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+pub fn info(args: fmt::Arguments) {
+    if enabled(Level::Info) {
+        progress::around(|| println!("{args}"));
+    }
+}
+
+// diagnostics go to stderr so they don't get mixed into output which is being parsed
+
+pub fn debug(args: fmt::Arguments) {
+    if enabled(Level::Debug) {
+        progress::around(|| eprintln!("debug: {args}"));
+    }
+}
+
+pub fn trace(args: fmt::Arguments) {
+    if enabled(Level::Trace) {
+        progress::around(|| eprintln!("trace: {args}"));
+    }
+}
@@ -1,216 +1,2830 @@
-use ddc::{Ddc, DdcHost as _};
-use ddc_hi::Display;
-use std::{ops::Neg, process::ExitCode};
+use ddc_brightness_ctl::{
+    Action, Backend, BrightnessChange, ChangeRow, ConfirmJumps, DEFAULT_RETRIES, Easing, Error,
+    ErrorKind, Fade, Feature, Gains, GetFeatures, GetFormat, Limits, Monitor, Options, Output,
+    PowerState, Preset, cache,
+    config::{self, Config},
+    daemon::{self, Request},
+    date::{self, ManufactureDate},
+    dedupe, display_name, get_brightness, i2c,
+    json::Json,
+    list::DisplayInfo,
+    lock::{self, Lock},
+    log::{self, Level},
+    notify, parse_color_preset, parse_input_source, print_change_table,
+    profile::{self, DisplaySettings, Profile},
+    progress, read_brightness,
+    schedule::{Schedule, parse_location, parse_time},
+    signal, supported, supports, timing, undo, wake,
+};
+use std::{
+    env,
+    ffi::OsString,
+    fmt, fs,
+    io::{self, IsTerminal, Read, Write},
+    ops::Neg,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 const RED: &str = "\x1B[31m";
+const YELLOW: &str = "\x1B[33m";
 const RESET: &str = "\x1B[0m";
 
-const LUMINANCE_FEATURE_CODE: u8 = 0x10;
+// long enough to catch the presses from a held key, short enough not to be noticed
+const COALESCE_WINDOW: Duration = Duration::from_millis(50);
+// the exit status when some displays, or script lines, failed but others succeeded
+const PARTIAL_FAILURE: u8 = 2;
+// the exit status of every failure with --exit-code, which no brightness can be mistaken for
+const VALUE_FAILURE: u8 = 255;
+// how far --inc and --dec change brightness without a value, as a percentage
+const DEFAULT_STEP: u16 = 5;
+// how long --wake-first gives displays to wake up without a value
+const DEFAULT_WAKE_WAIT: Duration = Duration::from_secs(1);
+// how many displays are talked to at once without --max-concurrency, enough for most setups
+// without having every bus busy at the same time
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
+// the features --show-features lists, by the names --get reads them under
+const LIST_FEATURES: [(&str, Feature); 5] = [
+    ("brightness", Feature::Luminance),
+    ("contrast", Feature::Contrast),
+    ("input", Feature::InputSource),
+    ("volume", Feature::Volume),
+    ("power", Feature::PowerMode),
+];
 
 struct Args {
     action: Action,
-    display: Option<usize>,
+    /// The displays to act on, all of them if empty.
+    display: Vec<DisplaySelector>,
+    exclude: Vec<DisplaySelector>,
+    /// Only acts on displays whose capabilities list every one of these VCP codes.
+    require_features: Vec<u8>,
     list: bool,
+    list_brightness: bool,
+    list_sort: ListSort,
+    date_format: DateFormat,
+    /// Only lists displays whose model contains this, ignoring case.
+    list_filter: Option<String>,
+    /// Only lists displays which support brightness.
+    list_only_brightness: bool,
+    /// Shows which of [`LIST_FEATURES`] each display supports with the list.
+    list_features: bool,
+    list_buses: bool,
+    /// The i2c device to use instead of enumerating displays.
+    bus: Option<PathBuf>,
+    /// Sets every display to the same brightness, the average of them all if None.
+    equalize: Option<Option<u16>>,
+    profile: Option<ProfileCommand>,
+    watch: Option<Duration>,
+    cache: CacheMode,
+    /// Keeps every display found, even those found more than once.
+    no_dedupe: bool,
+    fail_fast: bool,
+    /// How many displays are acted on at once.
+    max_concurrency: usize,
+    /// Fails rather than waiting when another invocation holds the lock.
+    no_wait: bool,
+    exit_with_value: bool,
+    daemon: bool,
+    /// Skips confirming destructive actions.
+    yes: bool,
+    /// Asks which display to use when several are selected.
+    interactive: bool,
+    /// Shows a desktop notification of the new value after a change.
+    notify: bool,
+    /// Switches displays on and waits this long before acting on them.
+    wake_first: Option<Duration>,
+    /// Switches displays off this long after acting on them.
+    off_after: Option<Duration>,
+    verbosity: Level,
+    /// Brightness limits for every display, overriding the config file.
+    min: Option<u16>,
+    max: Option<u16>,
+    /// Lets relative decreases go below the safe floor configured for a display.
+    force: bool,
+    /// A file of further arguments, each line of which is run against the same displays.
+    script: Option<PathBuf>,
+    config: Config,
+    options: Options,
 }
 
+enum ProfileCommand {
+    Save(String),
+    Load(String),
+    /// Loads the profile with the offset added to each display's saved brightness.
+    RelativeTo(String, i16),
+    /// Restores the brightness recorded before the last change.
+    Undo,
+    /// Loads the day or night profile, whichever the schedule says it is.
+    Auto(Schedule),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CacheMode {
+    Use,
+    Refresh,
+    Disabled,
+}
+
+/// The order `--list` shows displays in.
 #[derive(Clone, Copy)]
-enum Action {
-    Change(BrightnessChange),
-    Get,
+enum ListSort {
+    Index,
+    Model,
+    Serial,
 }
 
-impl Action {
-    fn is_noop(self) -> bool {
-        matches!(self, Action::Change(BrightnessChange::Relative(0)))
+impl ListSort {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "index" => Ok(Self::Index),
+            "model" => Ok(Self::Model),
+            "serial" => Ok(Self::Serial),
+            _ => Err(format!(
+                "invalid sort order '{s}', expected index, model or serial"
+            )),
+        }
     }
+}
 
-    fn execute(self, display: &mut Display, display_no: usize) -> ExitCode {
-        let mut exit_code = ExitCode::SUCCESS;
+/// How `--list` shows when displays were made.
+#[derive(Clone, Copy)]
+enum DateFormat {
+    /// The week and year, as the EDID gives them.
+    Week,
+    /// The date the week starts on.
+    Date,
+}
 
-        let model = display
-            .info
-            .model_name
-            .as_deref()
-            .unwrap_or("Unknown Model");
+impl DateFormat {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "week" => Ok(Self::Week),
+            "date" => Ok(Self::Date),
+            _ => Err(format!("invalid date format '{s}', expected week or date")),
+        }
+    }
+}
 
-        let disp = format!("display {display_no} ({model})");
+#[derive(Clone, Copy)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
 
-        let Ok(vcp) = display.handle.get_vcp_feature(LUMINANCE_FEATURE_CODE) else {
-            eprintln!("{RED}Timed out waiting for response from {disp}{RESET}");
-            return ExitCode::FAILURE;
-        };
-        let old_value = vcp.value();
-        display.handle.sleep();
+impl ColorMode {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "invalid colour mode '{s}', expected auto, always or never"
+            )),
+        }
+    }
+
+    // auto colours stderr only when it is a terminal and NO_COLOR isn't set
+    fn enabled(self) -> bool {
+        match self {
+            Self::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stderr().is_terminal()
+            }
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+fn red(color: bool, msg: &str) -> String {
+    if color {
+        format!("{RED}{msg}{RESET}")
+    } else {
+        msg.to_string()
+    }
+}
+
+// with --exit-code the brightness read is the exit status, so every failure has to use a status
+// it can't be, including those before the arguments have been parsed
+static EXIT_WITH_VALUE: AtomicBool = AtomicBool::new(false);
+
+fn failure() -> ExitCode {
+    if EXIT_WITH_VALUE.load(Ordering::Relaxed) {
+        ExitCode::from(VALUE_FAILURE)
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// some displays, or script lines, failed while others succeeded
+fn partial_failure() -> ExitCode {
+    if EXIT_WITH_VALUE.load(Ordering::Relaxed) {
+        ExitCode::from(VALUE_FAILURE)
+    } else {
+        ExitCode::from(PARTIAL_FAILURE)
+    }
+}
 
+// errors are printed at every verbosity, unlike `log::info`
+fn error(color: bool, msg: &str) {
+    eprintln!("{}", red(color, msg));
+}
+
+// for things which were skipped without failing the run, printed at every verbosity as errors are
+fn warn(color: bool, msg: &str) {
+    if color {
+        eprintln!("{YELLOW}{msg}{RESET}");
+    } else {
+        eprintln!("{msg}");
+    }
+}
+
+enum DisplaySelector {
+    Index(usize),
+    Serial(u32),
+    Model(String),
+    /// A DRM connector such as `DP-1`, which stands for the display on its DDC bus.
+    Connector {
+        name: String,
+        bus: PathBuf,
+    },
+    /// A name from the config's `[aliases]`, which stands for the display with the serial.
+    Alias {
+        name: String,
+        serial: u32,
+    },
+}
+
+impl DisplaySelector {
+    // a comma separated list of selectors, e.g. 0,2
+    fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        s.split(',')
+            .map(|selector| match selector.trim() {
+                "" => Err(format!("invalid display selection '{s}', empty entry")),
+                selector => Self::parse(selector),
+            })
+            .collect()
+    }
+
+    fn format_list(selectors: &[Self]) -> String {
+        let selectors: Vec<_> = selectors.iter().map(ToString::to_string).collect();
+        selectors.join(",")
+    }
+
+    // names given in the config's [aliases] take precedence over matching models
+    fn resolve_aliases(selectors: &mut [Self], config: &Config) {
+        for selector in selectors {
+            if let Self::Model(name) = selector
+                && let Some(serial) = config.alias(name)
+            {
+                *selector = Self::Alias {
+                    name: std::mem::take(name),
+                    serial,
+                };
+            }
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        if let Some(serial) = s.strip_prefix("serial:") {
+            return u32::from_str_radix(serial, 16)
+                .map(Self::Serial)
+                .map_err(|_| format!("invalid serial '{serial}', expected up to 8 hex digits"));
+        }
+
+        if let Ok(n) = s.parse() {
+            return Ok(Self::Index(n));
+        }
+
+        // connector names don't look like model names, so they are checked for first
+        match i2c::connector(s) {
+            Some(i2c::Connector {
+                name,
+                bus: Some(bus),
+            }) => Ok(Self::Connector { name, bus }),
+            Some(i2c::Connector { name, bus: None }) => Err(format!(
+                "connector {name} doesn't expose DDC/CI, so the display on it can't be controlled"
+            )),
+            None => Ok(Self::Model(s.to_string())),
+        }
+    }
+
+    fn matches(&self, index: usize, display: &Monitor) -> bool {
+        match self {
+            Self::Index(n) => *n == index,
+            Self::Serial(serial) | Self::Alias { serial, .. } => {
+                display.info.serial == Some(*serial)
+            }
+            Self::Model(name) => display
+                .info
+                .model_name
+                .as_deref()
+                .is_some_and(|model| model.to_lowercase().contains(&name.to_lowercase())),
+            Self::Connector { bus, .. } => i2c::bus_path(&display.info).as_ref() == Some(bus),
+        }
+    }
+}
+
+// formats the selector so that it parses back to the same selector
+impl fmt::Display for DisplaySelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Action::Change(brightness_change) => {
-                let new_value = brightness_change.apply(old_value);
-                if old_value == new_value {
-                    println!("No change needed for {disp}");
-                    return ExitCode::SUCCESS;
+            Self::Index(n) => write!(f, "{n}"),
+            Self::Serial(serial) | Self::Alias { serial, .. } => write!(f, "serial:{serial:08X}"),
+            Self::Model(name) | Self::Connector { name, .. } => f.write_str(name),
+        }
+    }
+}
+
+// disp names the display the error relates to, if any
+fn render_error(e: &Error, disp: Option<&str>) -> String {
+    let disp = disp.unwrap_or("display");
+    match e {
+        Error::Timeout(timeout, _) => format!(
+            "Timed out waiting for response from {disp} after {}ms, try a longer --timeout",
+            timeout.as_millis()
+        ),
+        Error::DdcUnavailable(_) => {
+            format!("{disp} may have DDC/CI disabled in its on-screen menu, it didn't respond")
+        }
+        Error::Communication(ErrorKind::Nak, e) => {
+            format!("{disp} didn't acknowledge the command: {e}")
+        }
+        Error::Communication(ErrorKind::Bus, e) => {
+            format!("Can't use the I2C bus of {disp}: {e}")
+        }
+        Error::Communication(_, e) => format!("Failed to communicate with {disp}: {e}"),
+        Error::SetFailed(feature, e) => format!("Failed to set {} for {disp}: {e}", feature.name()),
+        Error::PowerFailed(state, e) => format!(
+            "Failed to switch {disp} power to {} (VCP 0xD6 = 0x{:02X}): {e}",
+            state.name(),
+            state.value()
+        ),
+        Error::UnsupportedFeature(feature) => format!(
+            "{} not supported by {disp} (VCP 0x{:02X})",
+            feature.name(),
+            feature.code()
+        ),
+        Error::UnsupportedValue {
+            feature,
+            value,
+            supported,
+        } => {
+            let supported: Vec<_> = supported.iter().map(|v| feature.value_name(*v)).collect();
+            format!(
+                "{} {} not supported by {disp}, supported: {}",
+                feature.name(),
+                feature.value_name(*value),
+                supported.join(", ")
+            )
+        }
+        Error::Interrupted(feature) => format!(
+            "Interrupted changing {} of {disp}, it was restored",
+            feature.name()
+        ),
+        Error::Declined(feature) => format!(
+            "Not changing {} of {disp}, the change wasn't confirmed",
+            feature.name()
+        ),
+        Error::Channels(errors) => {
+            let errors: Vec<_> = errors.iter().map(|e| render_error(e, Some(disp))).collect();
+            errors.join("\n")
+        }
+        Error::Capabilities(e) => format!("Failed to read capabilities of {disp}: {e}"),
+        Error::Backlight(e) => format!("Failed to read backlight of {disp}: {e}"),
+        Error::NoSuchDisplay(selection) => format!("No display {selection}"),
+        Error::ParseArgs(e) => format!("Failed to parse arguments: {e}"),
+        Error::Config(e) => format!("Failed to load config: {e}"),
+    }
+}
+
+fn enumerate_displays(cache: CacheMode, no_dedupe: bool, options: Options) -> Vec<Monitor> {
+    // everything found is cached, so that --no-dedupe can still see the duplicates
+    let dedupe = |displays| {
+        if no_dedupe {
+            displays
+        } else {
+            dedupe(displays)
+        }
+    };
+
+    if cache == CacheMode::Use
+        && let Some(displays) = timing::time(None, || "load the display cache".into(), cache::load)
+    {
+        log::debug(format_args!(
+            "Loaded {} displays from the cache",
+            displays.len()
+        ));
+        return Monitor::from_displays(dedupe(displays));
+    }
+
+    // only worth saying to someone watching, it would end up mixed into piped output
+    if !options.json && io::stdout().is_terminal() {
+        log::info(format_args!("Querying display info... (~1-2 seconds)"));
+    }
+    let start = Instant::now();
+    let displays = timing::time(
+        None,
+        || "enumerate displays".into(),
+        ddc_hi::Display::enumerate,
+    );
+    log::debug(format_args!(
+        "Found {} displays over DDC/CI in {:?}",
+        displays.len(),
+        start.elapsed()
+    ));
+
+    if cache != CacheMode::Disabled
+        && let Err(e) = cache::store(&displays)
+    {
+        let msg = format!("Failed to write display cache: {e}");
+        error(options.color, &msg);
+    }
+
+    Monitor::from_displays(dedupe(displays))
+}
+
+#[cfg(unix)]
+fn run_daemon(
+    cache: CacheMode,
+    no_dedupe: bool,
+    options: Options,
+    per_display: &dyn Fn(&Monitor, Options) -> Options,
+) -> ExitCode {
+    let listener = match daemon::Listener::bind() {
+        Ok(listener) => listener,
+        Err(e) => {
+            let msg = format!("Failed to start the daemon: {e}");
+            error(options.color, &msg);
+            return failure();
+        }
+    };
+    let mut displays = enumerate_displays(cache, no_dedupe, options);
+    // the daemon's own errors still go to its stderr, coloured as asked for
+    let color = options.color;
+    // replies are printed by the client, which may not be writing to a terminal, and changes
+    // can't be confirmed as they come from elsewhere
+    let options = Options {
+        color: false,
+        confirm: None,
+        ..options
+    };
+
+    signal::catch_interrupt();
+    loop {
+        let batch = match listener.next_batch(COALESCE_WINDOW) {
+            Ok(Some(batch)) => batch,
+            Ok(None) => return ExitCode::SUCCESS,
+            Err(e) => {
+                error(color, &format!("Failed to accept request: {e}"));
+                return failure();
+            }
+        };
+
+        // requests for the same feature and displays are added up and applied as one change
+        let mut groups: Vec<(Request, Vec<daemon::Client>)> = Vec::new();
+        for (request, client) in batch {
+            let group = groups.iter_mut().find(|(pending, _)| {
+                pending.feature == request.feature && pending.display == request.display
+            });
+            match group {
+                Some((pending, clients)) => {
+                    pending.offset = pending.offset.saturating_add(request.offset);
+                    clients.push(client);
                 }
+                None => groups.push((request, vec![client])),
+            }
+        }
 
-                println!("Changing brighness of {disp} from {old_value} to {new_value}");
-                if let Err(e) = display
-                    .handle
-                    .set_vcp_feature(LUMINANCE_FEATURE_CODE, new_value)
-                {
-                    eprintln!("{RED}Failed to set brightness for {disp}: {e}{RESET}");
-                    exit_code = ExitCode::FAILURE;
+        for (request, clients) in groups {
+            let _lock = take_lock(true, options);
+            let mut out = Output::default();
+            let ok = apply_request(&request, &mut displays, options, per_display, &mut out);
+            for client in clients {
+                client.reply(&out, ok);
+            }
+        }
+    }
+}
+
+// takes the lock held while changing displays, continuing without it if it can't be created
+// as that is no reason not to make the change
+fn take_lock(wait: bool, options: Options) -> Result<Option<Lock>, ExitCode> {
+    let on_wait = || {
+        if !options.json {
+            log::info(format_args!(
+                "Waiting for another invocation to finish changing displays"
+            ));
+        }
+    };
+    match lock::acquire(wait, on_wait) {
+        Ok(lock) => Ok(lock),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+            let msg = "Another invocation is changing displays, not waiting as --no-wait was given";
+            error(options.color, msg);
+            Err(failure())
+        }
+        Err(e) => {
+            log::debug(format_args!(
+                "Failed to take the lock, continuing without it: {e}"
+            ));
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn run_daemon(
+    _cache: CacheMode,
+    _no_dedupe: bool,
+    options: Options,
+    _per_display: &dyn Fn(&Monitor, Options) -> Options,
+) -> ExitCode {
+    let msg = "The daemon is only supported on unix";
+    error(options.color, msg);
+    failure()
+}
+
+#[cfg(unix)]
+fn apply_request(
+    request: &Request,
+    displays: &mut [Monitor],
+    options: Options,
+    per_display: &dyn Fn(&Monitor, Options) -> Options,
+    out: &mut Output,
+) -> bool {
+    let selectors = match request.display.as_deref().map(DisplaySelector::parse_list) {
+        Some(Ok(selectors)) => selectors,
+        Some(Err(e)) => {
+            out.eprintln(format_args!("{e}"));
+            return false;
+        }
+        None => Vec::new(),
+    };
+
+    // like the command line, nothing is changed unless every selector matches
+    let unmatched = selectors.iter().find(|selector| {
+        !displays
+            .iter()
+            .enumerate()
+            .any(|(i, disp)| selector.matches(i, disp))
+    });
+    if let Some(selector) = unmatched {
+        let e = Error::NoSuchDisplay(selector.to_string());
+        out.eprintln(format_args!("{}", render_error(&e, None)));
+        return false;
+    }
+
+    let action = Action::Change(request.feature, BrightnessChange::Relative(request.offset));
+    let mut ok = true;
+    for (i, disp) in displays.iter_mut().enumerate() {
+        if !selectors.is_empty() && !selectors.iter().any(|selector| selector.matches(i, disp)) {
+            continue;
+        }
+
+        let options = per_display(disp, options);
+        if let Err(e) = action.execute(disp, i, options, out) {
+            out.eprintln(format_args!(
+                "{}",
+                render_error(&e, Some(&display_name(i, disp)))
+            ));
+            ok = false;
+        }
+    }
+
+    ok
+}
+
+// polls the brightness of each display until interrupted, printing a line whenever it changes
+fn watch_displays(
+    displays: &mut [(usize, &mut Monitor)],
+    interval: Duration,
+    per_display: impl Fn(&Monitor, Options) -> Options,
+    options: Options,
+) {
+    // None until the first poll, then the last brightness read or None if the read failed
+    let mut last: Vec<Option<Option<u16>>> = vec![None; displays.len()];
+
+    signal::catch_interrupt();
+    while !signal::interrupted() {
+        for ((i, disp), last) in displays.iter_mut().zip(&mut last) {
+            let result = read_brightness(disp, per_display(disp, options));
+            let current = result.as_ref().ok().copied();
+            if *last == Some(current) {
+                continue;
+            }
+            *last = Some(current);
+
+            let time = timestamp();
+            match result {
+                Ok(brightness) if options.json => {
+                    let info = Json::Object(vec![
+                        ("time", time.into()),
+                        ("display", (*i).into()),
+                        ("model", disp.info.model_name.as_deref().into()),
+                        ("brightness", brightness.into()),
+                    ]);
+                    println!("{info}");
+                }
+                Ok(brightness) => {
+                    println!(
+                        "{time} Brightness of {} is {brightness}%",
+                        display_name(*i, disp)
+                    );
                 }
-                display.handle.sleep();
+                Err(e) => {
+                    let msg = render_error(&e, Some(&display_name(*i, disp)));
+                    error(options.color, &format!("{time} {msg}"));
+                }
+            }
+        }
+
+        // sleep in short steps so Ctrl-C is handled promptly
+        let mut remaining = interval;
+        while !remaining.is_zero() && !signal::interrupted() {
+            let step = remaining.min(Duration::from_millis(50));
+            thread::sleep(step);
+            remaining -= step;
+        }
+    }
+}
+
+// counts down `wait` then switches the displays off, unless Ctrl-C is pressed first which leaves
+// them on, returning whether every display was switched off
+fn switch_off_after(
+    displays: &mut [(usize, &mut Monitor)],
+    wait: Duration,
+    per_display: impl Fn(&Monitor, Options) -> Options,
+    options: Options,
+) -> bool {
+    if !options.dry_run {
+        let secs = wait.as_secs();
+        let Some(deadline) = Instant::now().checked_add(wait) else {
+            let msg = format!("Can't wait {secs}s to switch off, it's too long");
+            error(options.color, &msg);
+            return false;
+        };
+        if !options.json {
+            log::info(format_args!(
+                "Switching off in {secs}s, press Ctrl-C to leave the displays on"
+            ));
+        }
+        let mut shown = None;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            if signal::interrupted() {
+                log::info(format_args!("Cancelled, leaving the displays on"));
+                return true;
             }
-            Action::Get => {
-                println!("{disp} is set to {old_value}% brightness");
+            // whole seconds left, rounded up so that it never shows 0 before switching off
+            let secs = remaining.as_millis().div_ceil(1000);
+            if shown != Some(secs) {
+                log::debug(format_args!("Switching off in {secs}s"));
+                shown = Some(secs);
             }
+            thread::sleep(remaining.min(Duration::from_millis(50)));
         }
+    }
 
-        exit_code
+    let mut ok = true;
+    for (i, disp) in displays.iter_mut() {
+        let options = per_display(disp, options);
+        let mut out = Output::new(options);
+        let result = Action::Power(PowerState::Off).execute(disp, *i, options, &mut out);
+        out.flush();
+        if let Err(e) = result {
+            error(
+                options.color,
+                &render_error(&e, Some(&display_name(*i, disp))),
+            );
+            ok = false;
+        }
     }
+    ok
 }
 
-#[derive(Clone, Copy)]
-enum BrightnessChange {
-    Relative(i16),
-    Absolute(u16),
+// the current time in UTC as RFC 3339, e.g. 2024-05-01T18:30:00.250Z
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    let (year, month, day) = date::civil_from_days(days as i64);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        now.subsec_millis()
+    )
 }
 
-impl BrightnessChange {
-    fn apply(self, value: u16) -> u16 {
-        match self {
-            Self::Relative(offset) => {
-                let default = if offset < 0 { 0 } else { 100 };
-                value.checked_add_signed(offset).unwrap_or(default)
+// the average brightness of the displays, leaving out any which can't be read so that they
+// don't skew it
+fn average_brightness(
+    displays: &mut [(usize, &mut Monitor)],
+    per_display: impl Fn(&Monitor, Options) -> Options,
+    options: Options,
+) -> Option<u16> {
+    let mut readings = Vec::new();
+    for (i, disp) in displays.iter_mut() {
+        match read_brightness(disp, per_display(disp, options)) {
+            Ok(brightness) => readings.push(u32::from(brightness)),
+            Err(e) => {
+                let msg = render_error(&e, Some(&display_name(*i, disp)));
+                let msg = format!("{msg}, leaving it out of the average");
+                error(options.color, &msg);
             }
-            Self::Absolute(value) => value,
         }
-        .clamp(0, 100)
+    }
+
+    let count = readings.len() as u32;
+    (count > 0).then(|| ((readings.iter().sum::<u32>() + count / 2) / count) as u16)
+}
+
+// runs `work` on each job with at most `limit` running at once, as talking to every display at
+// the same time can swamp the i2c buses, returning the results in the order of the jobs
+fn in_parallel<T: Send, R: Send>(
+    jobs: Vec<T>,
+    limit: usize,
+    work: impl Fn(T) -> R + Sync,
+) -> Vec<R> {
+    let total = jobs.len();
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let mut results: Vec<_> = thread::scope(|s| {
+        let workers: Vec<_> = (0..limit.min(total))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    // jobs not yet started are left alone once interrupted
+                    while !signal::interrupted() {
+                        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).next();
+                        let Some((n, job)) = next else { break };
+                        done.push((n, work(job)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("display worker panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|&(n, _)| n);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// brightness limits and timeouts can be set per display in the config, shared by normal runs,
+// scripts and the daemon so that they all treat each display the same
+fn per_display_options(
+    config: &Config,
+    disp: &Monitor,
+    min: Option<u16>,
+    max: Option<u16>,
+    force: bool,
+    options: Options,
+) -> Options {
+    Options {
+        limits: config.limits(disp.info.serial, min, max, force),
+        timeout: config.timeout(disp.info.serial, options.timeout),
+        ..options
+    }
+}
+
+// profiles are keyed by serial, so displays which don't report one can't be saved
+fn save_profile(
+    name: &str,
+    displays: &mut [(usize, &mut Monitor)],
+    max_concurrency: usize,
+    options: Options,
+) -> ExitCode {
+    let jobs = displays.iter_mut().collect();
+    let results = in_parallel(jobs, max_concurrency, |(i, disp)| {
+        (*i, get_brightness(disp))
+    });
+
+    let mut exit_code = ExitCode::SUCCESS;
+    let mut profile = Profile::default();
+    for ((i, disp), (_, result)) in displays.iter().zip(results) {
+        let disp_name = display_name(*i, disp);
+        let Some(serial) = disp.info.serial else {
+            let msg = format!("{disp_name} has no serial number, skipping");
+            error(options.color, &msg);
+            continue;
+        };
+
+        match result {
+            Ok(brightness) => profile.displays.push(DisplaySettings {
+                serial,
+                model: disp.info.model_name.clone(),
+                brightness: Some(brightness),
+            }),
+            Err(e) => {
+                let msg = render_error(&e, Some(&disp_name));
+                error(options.color, &msg);
+                exit_code = failure();
+            }
+        }
+    }
+
+    if let Err(e) = profile::save(name, &profile) {
+        let msg = format!("Failed to save profile {name}: {e}");
+        error(options.color, &msg);
+        return failure();
+    }
+    if !options.json {
+        log::info(format_args!(
+            "Saved {} displays to profile {name}",
+            profile.displays.len()
+        ));
+    }
+
+    exit_code
+}
+
+// the commit and build date are only known when the builder sets them, such as packagers
+// building from a git checkout
+fn print_version(json: bool) {
+    let name = env!("CARGO_PKG_NAME");
+    let version = env!("CARGO_PKG_VERSION");
+    if !json {
+        println!("{name} v{version}");
+        return;
+    }
+
+    let commit = option_env!("DDC_BRIGHTNESS_CTL_COMMIT").filter(|commit| !commit.is_empty());
+    // SOURCE_DATE_EPOCH is how reproducible builds pin the build time
+    let build_date = option_env!("SOURCE_DATE_EPOCH")
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .map(|secs| {
+            let (year, month, day) = date::civil_from_days((secs / 86400) as i64);
+            format!("{year:04}-{month:02}-{day:02}")
+        });
+    let info = Json::Object(vec![
+        ("name", name.into()),
+        ("version", version.into()),
+        ("commit", commit.into()),
+        ("build_date", build_date.into()),
+    ]);
+    println!("{info}");
+}
+
+fn completion_script(shell: &str) -> Result<&'static str, String> {
+    match shell {
+        "bash" => Ok(include_str!("../completions/ddc-brightness-ctl.bash")),
+        "zsh" => Ok(include_str!("../completions/_ddc-brightness-ctl")),
+        "fish" => Ok(include_str!("../completions/ddc-brightness-ctl.fish")),
+        _ => Err(format!(
+            "unsupported shell '{shell}', expected bash, zsh or fish"
+        )),
     }
 }
 
-fn parse_args() -> Result<Args, lexopt::Error> {
+// a number, which may be written with a % after it when it's a percentage, such as 50%; only
+// the one is taken off, so that anything more mangled still fails to parse
+fn parse_number<T: FromStr>(s: &str) -> Result<T, T::Err> {
+    s.strip_suffix('%').unwrap_or(s).parse()
+}
+
+fn parse_toggle(s: &str) -> Result<(u16, u16), String> {
+    let (low, high) = s
+        .split_once(',')
+        .ok_or_else(|| format!("invalid toggle levels '{s}', expected LOW,HIGH"))?;
+    let parse = |level: &str| {
+        parse_number::<u16>(level.trim())
+            .map_err(|e| format!("invalid toggle level '{level}': {e}"))
+    };
+
+    Ok((parse(low)?, parse(high)?))
+}
+
+// MS or MS:EASE, fades without an easing are linear
+fn parse_fade(s: &str) -> Result<Fade, String> {
+    let (ms, easing) = match s.split_once(':') {
+        Some((ms, easing)) => (ms, Easing::parse(easing)?),
+        None => (s, Easing::Linear),
+    };
+    let ms = ms
+        .parse()
+        .map_err(|e| format!("invalid fade duration '{ms}': {e}"))?;
+
+    Ok(Fade {
+        duration: Duration::from_millis(ms),
+        easing,
+    })
+}
+
+// VCP codes are conventionally written in hex, so they're parsed as hex with or without 0x
+fn parse_vcp_code(s: &str) -> Result<u8, String> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u8::from_str_radix(hex, 16).map_err(|_| format!("invalid VCP code '{s}', expected a hex byte"))
+}
+
+fn parse_vcp_set(s: &str) -> Result<(u8, u16), String> {
+    let (code, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid VCP write '{s}', expected CODE=VALUE"))?;
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    let value = parsed.map_err(|e| format!("invalid VCP value '{value}': {e}"))?;
+
+    Ok((parse_vcp_code(code)?, value))
+}
+
+// an offset from another brightness, e.g. -10
+fn parse_offset(s: &str) -> Result<i16, String> {
+    match parse_number(s) {
+        Ok(offset @ -100..=100) => Ok(offset),
+        _ => Err(format!("invalid offset '{s}', expected -100 to 100")),
+    }
+}
+
+fn parse_percent(s: &str) -> Result<u16, String> {
+    match parse_number(s) {
+        Ok(percent @ 0..=100) => Ok(percent),
+        _ => Err(format!("invalid percentage '{s}', expected 0 to 100")),
+    }
+}
+
+// a percentage to scale by, which f32 would otherwise happily parse from nan or inf
+fn parse_factor_percent(s: &str) -> Result<f32, String> {
+    match parse_number::<f32>(s) {
+        Ok(percent) if percent.is_finite() => Ok(percent),
+        Ok(_) => Err(format!("invalid percentage '{s}', expected a number")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// whether to go ahead with changes which need confirming when there is no one to ask
+fn parse_unattended(s: &str) -> Result<bool, String> {
+    match s {
+        "proceed" => Ok(true),
+        "fail" => Ok(false),
+        _ => Err(format!("invalid value '{s}', expected proceed or fail")),
+    }
+}
+
+// the preset named `name` in the config, with its colour preset parsed
+fn find_preset(config: &Config, name: &str) -> Result<Preset, lexopt::Error> {
+    let Some(preset) = config.preset(name) else {
+        let names: Vec<_> = config
+            .presets
+            .iter()
+            .map(|preset| preset.name.as_str())
+            .collect();
+        let msg = if names.is_empty() {
+            format!("no preset {name}, none are defined in the config")
+        } else {
+            format!("no preset {name}, available presets: {}", names.join(", "))
+        };
+        return Err(lexopt::Error::Custom(msg.into()));
+    };
+
+    let color_preset = match &preset.color_temp {
+        Some(color_temp) => Some(parse_color_preset(color_temp).map_err(|e| {
+            lexopt::Error::Custom(format!("invalid color-temp in preset {name}: {e}").into())
+        })?),
+        None => None,
+    };
+    Ok(Preset {
+        brightness: preset.brightness,
+        contrast: preset.contrast,
+        color_preset,
+    })
+}
+
+// the indices of the displays --list shows, stably sorted so that ties stay in index order
+fn list_order(displays: &[Monitor], sort: ListSort, filter: Option<&str>) -> Vec<usize> {
+    let filter = filter.map(str::to_lowercase);
+    let mut listed: Vec<_> = displays
+        .iter()
+        .enumerate()
+        .filter(|(_, disp)| {
+            filter.as_ref().is_none_or(|filter| {
+                disp.info
+                    .model_name
+                    .as_deref()
+                    .is_some_and(|model| model.to_lowercase().contains(filter))
+            })
+        })
+        .collect();
+
+    // displays missing the key go last
+    match sort {
+        ListSort::Index => {}
+        ListSort::Model => listed.sort_by_key(|(_, disp)| {
+            let model = disp.info.model_name.as_deref().map(str::to_lowercase);
+            (model.is_none(), model)
+        }),
+        ListSort::Serial => {
+            listed.sort_by_key(|(_, disp)| (disp.info.serial.is_none(), disp.info.serial))
+        }
+    }
+
+    listed.into_iter().map(|(i, _)| i).collect()
+}
+
+// the metadata shown for a display by --list, e.g.
+// `[0]: DELL U2720Q - (DEL:A0B1:1A2B3C4D), manufactured week 12 of 2021`
+fn list_entry(i: usize, disp: &Monitor, date_format: DateFormat) -> String {
+    let manufactured = match (ManufactureDate::of(&disp.info), date_format) {
+        (Some(ManufactureDate::Week { week, year }), DateFormat::Week) => {
+            format!("manufactured week {week} of {year}")
+        }
+        (Some(date @ ManufactureDate::Week { .. }), DateFormat::Date) => {
+            let (year, month, day) = date.approximate_date().expect("the week is known");
+            format!("manufactured around {year:04}-{month:02}-{day:02}")
+        }
+        (Some(ManufactureDate::Year(year)), _) => format!("manufactured in {year}"),
+        (Some(ManufactureDate::ModelYear(year)), _) => format!("model year {year}"),
+        (None, _) => "manufacture date unknown".to_string(),
+    };
+    format!(
+        "[{i}]: {} - ({}:{}:{}), {manufactured}",
+        disp.info.model_name.as_deref().unwrap_or("Unknown Model"),
+        disp.info.manufacturer_id.as_deref().unwrap_or("???"),
+        disp.info
+            .model_id
+            .map(|num| format!("{num:04X}"))
+            .as_deref()
+            .unwrap_or("????"),
+        disp.info
+            .serial
+            .map(|num| format!("{num:08X}"))
+            .as_deref()
+            .unwrap_or("????????"),
+    )
+}
+
+// lists the displays and asks which one to use, any answer but one of their numbers gives up
+// rather than asking again, so that a closed stdin can't loop forever
+fn pick_display(
+    displays: &[(usize, &mut Monitor)],
+    date_format: DateFormat,
+) -> Result<usize, String> {
+    eprintln!("Displays:");
+    for (i, disp) in displays {
+        eprintln!("  - {}", list_entry(*i, disp, date_format));
+    }
+    eprint!("Choose a display: ");
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) => return Err("No display chosen, stdin was closed".to_string()),
+        Ok(_) => {}
+        Err(e) => return Err(format!("Failed to read the chosen display: {e}")),
+    }
+
+    let answer = answer.trim();
+    answer
+        .parse()
+        .ok()
+        .filter(|picked| displays.iter().any(|(i, _)| i == picked))
+        .ok_or_else(|| {
+            let numbers: Vec<_> = displays.iter().map(|(i, _)| i.to_string()).collect();
+            format!(
+                "Invalid choice '{answer}', expected one of {}",
+                numbers.join(", ")
+            )
+        })
+}
+
+// asks on stderr, so the prompt isn't mixed into output being parsed, anything but yes declines
+fn confirm(prompt: &str) -> bool {
+    eprint!("{prompt} [y/N] ");
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+// the gains being set, so that e.g. --red and --blue can be given together
+fn gains(action: &mut Action) -> &mut Gains {
+    if !matches!(action, Action::SetGains(_)) {
+        *action = Action::SetGains(Gains::default());
+    }
+    match action {
+        Action::SetGains(gains) => gains,
+        _ => unreachable!(),
+    }
+}
+
+// a bare NUM sets the brightness, while +NUM and -NUM change it by that much
+fn parse_quick_change(s: &str) -> Result<BrightnessChange, String> {
+    let invalid = |_| format!("invalid brightness '{s}', expected NUM, +NUM or -NUM");
+    if let Some(offset) = s.strip_prefix('+') {
+        return parse_number(offset)
+            .map(BrightnessChange::Relative)
+            .map_err(invalid);
+    }
+    if s.starts_with('-') {
+        return parse_number(s)
+            .map(BrightnessChange::Relative)
+            .map_err(invalid);
+    }
+
+    parse_number(s)
+        .map(BrightnessChange::Absolute)
+        .map_err(invalid)
+}
+
+// the value of an option which may be left out, given either as --opt=NUM or as --opt NUM as
+// long as the next argument is a number, so that a following option is never taken for it
+fn optional_number(parser: &mut lexopt::Parser) -> Option<OsString> {
+    parser.optional_value().or_else(|| {
+        parser.try_raw_args()?.next_if(|arg| {
+            arg.to_str()
+                .is_some_and(|arg| parse_number::<u16>(arg).is_ok())
+        })
+    })
+}
+
+// a value of `-` is read from stdin instead, so that it can be piped in, and `env:NAME` from
+// the environment variable NAME
+fn parse_set_value(value: OsString) -> Result<u16, lexopt::Error> {
+    use lexopt::ValueExt;
+
+    if let Some(name) = value.to_str().and_then(|value| value.strip_prefix("env:")) {
+        let value = env::var(name).map_err(|e| match e {
+            env::VarError::NotPresent => format!("environment variable {name} is not set"),
+            env::VarError::NotUnicode(_) => format!("environment variable {name} is not unicode"),
+        })?;
+        return parse_number(value.trim()).map_err(|_| {
+            format!("invalid value '{value}' in environment variable {name}, expected NUM").into()
+        });
+    }
+    if value != "-" {
+        return value.parse_with(parse_number);
+    }
+
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| format!("failed to read value from stdin: {e}"))?;
+    OsString::from(input.trim()).parse_with(parse_number)
+}
+
+// config values are parsed like the options they stand in for
+fn config_value<T>(
+    key: &str,
+    value: Option<&str>,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, Error> {
+    value.map(parse).transpose().map_err(|e| {
+        Error::Config(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{key}: {e}"),
+        ))
+    })
+}
+
+fn parse_args(mut parser: lexopt::Parser) -> Result<Args, Error> {
     use lexopt::prelude::*;
 
-    let mut parser = lexopt::Parser::from_env();
-    let mut display = None;
-    let mut action = Action::Get;
+    let mut display = Vec::new();
+    let mut exclude = Vec::new();
+    let mut exclude_models = Vec::new();
+    let mut require_features = Vec::new();
+    let mut action = Action::Get(GetFeatures::default());
     let mut list = false;
-    while let Some(arg) = parser.next()? {
+    let mut list_brightness = false;
+    let mut list_sort = ListSort::Index;
+    let mut list_filter = None;
+    let mut list_only_brightness = false;
+    let mut list_features = false;
+    let mut date_format = DateFormat::Week;
+    let mut list_buses = false;
+    let mut bus = None;
+    let mut equalize = None;
+    let mut profile = None;
+    let mut relative_to = None;
+    let mut offset = None;
+    // --relative-to does what --sync-from does, but is the only one --offset goes with
+    let mut synced_by_sync_from = false;
+    let mut wake_first = None;
+    let mut off_after = None;
+    let mut version = false;
+    let mut preset = None;
+    let mut watch = false;
+    let mut interval = Duration::from_secs(1);
+    let mut json = false;
+    let mut fade = None;
+    let mut retries = None;
+    let mut delay = None;
+    let mut timeout = None;
+    let mut cache = CacheMode::Use;
+    let mut fail_fast = false;
+    let mut max_concurrency = DEFAULT_MAX_CONCURRENCY;
+    let mut no_wait = false;
+    let mut no_dedupe = false;
+    let mut exit_with_value = false;
+    let mut daemon = false;
+    let mut color = None;
+    let mut config_path = None;
+    let mut use_config = true;
+    let mut verbosity = Level::Info;
+    let mut dry_run = false;
+    let mut raw = false;
+    let mut perceptual = false;
+    let mut format = GetFormat::Text;
+    let mut yes = false;
+    let mut confirm_delta = None;
+    let mut interactive = false;
+    let mut notify = false;
+    let mut auto = false;
+    let mut location = None;
+    let mut sunrise = None;
+    let mut sunset = None;
+    let mut min = None;
+    let mut max = None;
+    let mut force = false;
+    let mut verify = false;
+    let mut step = None;
+    let mut script = None;
+    // +1 or -1 when --inc or --dec were given without a value, which the step is applied in
+    let mut step_direction = None;
+    // whether a change was written as a percentage, which --raw values can't be
+    let mut percent_given = false;
+    let mut percent_sign = |value: OsString| {
+        percent_given |= value.to_str().is_some_and(|value| value.ends_with('%'));
+        value
+    };
+    loop {
+        // lexopt would take a negative number for a cluster of short options
+        let negative = parser.try_raw_args().and_then(|mut raw| {
+            raw.next_if(|arg| {
+                arg.to_str()
+                    .and_then(|arg| arg.strip_prefix('-'))
+                    .is_some_and(|number| parse_number::<u16>(number).is_ok())
+            })
+        });
+        let arg = match negative {
+            Some(value) => Value(value),
+            None => match parser.next()? {
+                Some(arg) => arg,
+                None => break,
+            },
+        };
+
         match arg {
+            Value(value) => {
+                let change = percent_sign(value).parse_with(parse_quick_change)?;
+                action = Action::Change(Feature::Luminance, change);
+            }
             Short('d') | Long("display") => {
-                display = Some(parser.value()?.parse()?);
+                display = parser.value()?.parse_with(DisplaySelector::parse_list)?;
+            }
+            Long("exclude") => {
+                exclude.extend(parser.value()?.parse_with(DisplaySelector::parse_list)?);
+            }
+            Long("exclude-model") => {
+                exclude_models.push(DisplaySelector::Model(parser.value()?.string()?));
+            }
+            Long("require-feature") => {
+                require_features.push(parser.value()?.parse_with(parse_vcp_code)?);
             }
-            Long("inc") => {
-                action = Action::Change(BrightnessChange::Relative(parser.value()?.parse()?));
+            Long(flag @ ("inc" | "dec")) => {
+                let direction = if flag == "inc" { 1 } else { -1 };
+                let offset = match optional_number(&mut parser) {
+                    Some(value) => percent_sign(value).parse_with(parse_number::<i16>)? * direction,
+                    None => {
+                        step_direction = Some(direction);
+                        0
+                    }
+                };
+                action = Action::Change(Feature::Luminance, BrightnessChange::Relative(offset));
             }
-            Long("dec") => {
-                action = Action::Change(BrightnessChange::Relative(
-                    parser.value()?.parse::<i16>()?.neg(),
-                ));
+            Long("step") => step = Some(parser.value()?.parse_with(parse_percent)?),
+            Long("inc-pct") => {
+                let pct = parser.value()?.parse_with(parse_factor_percent)?;
+                action = Action::Change(
+                    Feature::Luminance,
+                    BrightnessChange::RelativeFactor(1.0 + pct / 100.0),
+                );
+            }
+            Long("dec-pct") => {
+                let pct = parser.value()?.parse_with(parse_factor_percent)?;
+                action = Action::Change(
+                    Feature::Luminance,
+                    BrightnessChange::RelativeFactor(1.0 - pct / 100.0),
+                );
             }
             Long("set") => {
-                action = Action::Change(BrightnessChange::Absolute(parser.value()?.parse()?))
+                let value = parse_set_value(percent_sign(parser.value()?))?;
+                action = Action::Change(Feature::Luminance, BrightnessChange::Absolute(value))
+            }
+            Long("toggle") => {
+                let (low, high) = percent_sign(parser.value()?).parse_with(parse_toggle)?;
+                action = Action::Change(Feature::Luminance, BrightnessChange::Toggle { low, high });
+            }
+            Long("contrast-inc") => {
+                action = Action::Change(
+                    Feature::Contrast,
+                    BrightnessChange::Relative(
+                        percent_sign(parser.value()?).parse_with(parse_number)?,
+                    ),
+                );
+            }
+            Long("contrast-dec") => {
+                action = Action::Change(
+                    Feature::Contrast,
+                    BrightnessChange::Relative(
+                        percent_sign(parser.value()?)
+                            .parse_with(parse_number::<i16>)?
+                            .neg(),
+                    ),
+                );
+            }
+            Long("contrast-set") => {
+                action = Action::Change(
+                    Feature::Contrast,
+                    BrightnessChange::Absolute(
+                        percent_sign(parser.value()?).parse_with(parse_number)?,
+                    ),
+                )
+            }
+            Long("volume-inc") => {
+                action = Action::Change(
+                    Feature::Volume,
+                    BrightnessChange::Relative(
+                        percent_sign(parser.value()?).parse_with(parse_number)?,
+                    ),
+                );
+            }
+            Long("volume-dec") => {
+                action = Action::Change(
+                    Feature::Volume,
+                    BrightnessChange::Relative(
+                        percent_sign(parser.value()?)
+                            .parse_with(parse_number::<i16>)?
+                            .neg(),
+                    ),
+                );
+            }
+            Long("volume-set") => {
+                action = Action::Change(
+                    Feature::Volume,
+                    BrightnessChange::Absolute(
+                        percent_sign(parser.value()?).parse_with(parse_number)?,
+                    ),
+                )
+            }
+            Long("red") => {
+                gains(&mut action).red = Some(parser.value()?.parse_with(parse_percent)?)
+            }
+            Long("green") => {
+                gains(&mut action).green = Some(parser.value()?.parse_with(parse_percent)?);
+            }
+            Long("blue") => {
+                gains(&mut action).blue = Some(parser.value()?.parse_with(parse_percent)?)
+            }
+            Long("input") => {
+                action = Action::SetInput(parser.value()?.parse_with(parse_input_source)?)
+            }
+            Long("get") => {
+                action = Action::Get(match parser.optional_value() {
+                    Some(value) => value.parse_with(GetFeatures::parse)?,
+                    None => GetFeatures::default(),
+                });
+            }
+            Long("identify") => action = Action::Identify,
+            Long(flag @ ("sync-from" | "relative-to")) => {
+                synced_by_sync_from = flag == "sync-from";
+                action = Action::SyncFrom(parser.value()?.parse()?, 0);
+            }
+            Long("offset") => offset = Some(parser.value()?.parse_with(parse_offset)?),
+            Long("equalize") => {
+                equalize = Some(
+                    parser
+                        .optional_value()
+                        .map(|v| v.parse_with(parse_percent))
+                        .transpose()?,
+                );
+            }
+            Long("capabilities") => action = Action::Capabilities,
+            Long("features") => action = Action::Features,
+            Long("watch") => watch = true,
+            Long("interval") => interval = Duration::from_millis(parser.value()?.parse()?),
+            Long("undo") => profile = Some(ProfileCommand::Undo),
+            Long("auto") => auto = true,
+            Long("location") => location = Some(parser.value()?.parse_with(parse_location)?),
+            Long("sunrise") => sunrise = Some(parser.value()?.parse_with(parse_time)?),
+            Long("sunset") => sunset = Some(parser.value()?.parse_with(parse_time)?),
+            Long("save-profile") => profile = Some(ProfileCommand::Save(parser.value()?.string()?)),
+            Long("load-profile") => profile = Some(ProfileCommand::Load(parser.value()?.string()?)),
+            Long("set-relative-to") => relative_to = Some(parser.value()?.string()?),
+            Long("wake-first") => {
+                wake_first = Some(match optional_number(&mut parser) {
+                    Some(wait) => Duration::from_millis(wait.parse()?),
+                    None => DEFAULT_WAKE_WAIT,
+                });
+            }
+            Long("off-after") => off_after = Some(Duration::from_secs(parser.value()?.parse()?)),
+            Long("preset") => preset = Some(parser.value()?.string()?),
+            Long("color-temp") => {
+                action = Action::ColorPreset(parser.value()?.parse_with(parse_color_preset)?)
+            }
+            Long("power") => action = Action::Power(parser.value()?.parse_with(PowerState::parse)?),
+            Long("vcp-get") => action = Action::VcpGet(parser.value()?.parse_with(parse_vcp_code)?),
+            Long("vcp-set") => {
+                let (code, value) = parser.value()?.parse_with(parse_vcp_set)?;
+                action = Action::VcpSet { code, value };
             }
-            Long("get") => action = Action::Get,
             Short('l') | Long("list") => list = true,
-            Short('v') | Long("version") => {
-                println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Long("list-buses") => list_buses = true,
+            Long("bus") => bus = Some(parser.value()?.parse_with(i2c::parse_bus)?),
+            Long("brightness") => list_brightness = true,
+            Long("sort") => list_sort = parser.value()?.parse_with(ListSort::parse)?,
+            Long("date-format") => date_format = parser.value()?.parse_with(DateFormat::parse)?,
+            Long("filter") => list_filter = Some(parser.value()?.string()?),
+            Long("only-brightness") => list_only_brightness = true,
+            Long("show-features") => list_features = true,
+            Long("json") => json = true,
+            Long("raw") => raw = true,
+            Long("perceptual") => perceptual = true,
+            Long("format") => format = parser.value()?.parse_with(GetFormat::parse)?,
+            Long("fade") => fade = Some(parser.value()?.parse_with(parse_fade)?),
+            Long("retries") => retries = Some(parser.value()?.parse()?),
+            Long("delay") => delay = Some(Duration::from_millis(parser.value()?.parse()?)),
+            Long("timeout") => timeout = Some(Duration::from_millis(parser.value()?.parse()?)),
+            Long("no-cache") => cache = CacheMode::Disabled,
+            Long("refresh-cache") => cache = CacheMode::Refresh,
+            Long("fail-fast") => fail_fast = true,
+            Long("max-concurrency") => max_concurrency = parser.value()?.parse()?,
+            Long("no-wait") => no_wait = true,
+            Long("no-dedupe") => no_dedupe = true,
+            Long("notify") => notify = true,
+            Long("script") => script = Some(PathBuf::from(parser.value()?)),
+            Long("dry-run") => dry_run = true,
+            Long("verify") => verify = true,
+            Long("timing") => timing::enable(),
+            Long("reset") => action = Action::Reset,
+            Short('y') | Long("yes") => yes = true,
+            Long("confirm") => confirm_delta = Some(parser.value()?.parse_with(parse_percent)?),
+            Long("interactive") => interactive = true,
+            Long("min") => min = Some(parser.value()?.parse_with(parse_percent)?),
+            Long("max") => max = Some(parser.value()?.parse_with(parse_percent)?),
+            Long("force") => force = true,
+            Long("exit-code") => exit_with_value = true,
+            Long("daemon") => daemon = true,
+            Long("color") => color = Some(parser.value()?.parse_with(ColorMode::parse)?),
+            Long("config") => config_path = Some(PathBuf::from(parser.value()?)),
+            Long("no-config") => use_config = false,
+            // hidden from --help, as it's meant for packagers
+            Long("completions") => {
+                print!("{}", parser.value()?.parse_with(completion_script)?);
                 std::process::exit(0);
             }
+            Short('q') | Long("quiet") => verbosity = Level::Quiet,
+            Short('v') | Long("verbose") => verbosity = verbosity.more(),
+            Short('V') | Long("version") => version = true,
             Short('h') | Long("help") => {
-                println!("Usage: ddc-brightness-ctl [-h|--help] [-v|--version] [-d|--display=NUM] [-l|--list] [--inc=NUM] [--dec=NUM] [--set=NUM]");
+                println!("Usage: ddc-brightness-ctl [NUM|+NUM|-NUM] [-h|--help] [-V|--version] [-q|--quiet] [-v|--verbose] [-d|--display=NUM|serial:SERIAL|CONNECTOR|MODEL] [--exclude=NUM|serial:SERIAL|MODEL] [--exclude-model=MODEL] [--require-feature=CODE] [-l|--list] [--list-buses] [--bus=N|PATH] [--interactive] [--brightness] [--sort=KEY] [--date-format=FORMAT] [--filter=MODEL] [--only-brightness] [--show-features] [--get[=FEATURES]] [--format=FORMAT] [--inc[=NUM]] [--dec[=NUM]] [--step=NUM] [--perceptual] [--inc-pct=NUM] [--dec-pct=NUM] [--set=NUM] [--toggle=LOW,HIGH] [--equalize[=NUM]] [--sync-from=NUM] [--relative-to=NUM] [--offset=NUM] [--contrast-inc=NUM] [--contrast-dec=NUM] [--contrast-set=NUM] [--volume-inc=NUM] [--volume-dec=NUM] [--volume-set=NUM] [--red=NUM] [--green=NUM] [--blue=NUM] [--input=NAME] [--identify] [--capabilities] [--features] [--color-temp=PRESET] [--preset=NAME] [--power=STATE] [--vcp-get=CODE] [--vcp-set=CODE=VALUE] [--reset] [-y|--yes] [--confirm=NUM] [--save-profile=NAME] [--load-profile=NAME] [--set-relative-to=NAME] [--undo] [--auto] [--location=LAT,LON] [--sunrise=HH:MM] [--sunset=HH:MM] [--watch] [--interval=MS] [--json] [--raw] [--fade=MS[:EASE]] [--retries=N] [--delay=MS] [--timeout=MS] [--no-cache] [--refresh-cache] [--no-dedupe] [--notify] [--wake-first[=MS]] [--off-after=SECONDS] [--fail-fast] [--max-concurrency=N] [--no-wait] [--script=FILE] [--dry-run] [--verify] [--timing] [--min=NUM] [--max=NUM] [--force] [--exit-code] [--daemon] [--color=WHEN] [--config=PATH] [--no-config]");
                 println!();
                 println!("Options:");
                 println!("  -d,    --display: optionally specify which display to change");
+                println!("                    by number, serial:SERIAL, connector name such");
+                println!("                    as DP-1 or a case insensitive part of its model");
+                println!("                    name, or a comma separated list of these,");
                 println!("                    default operates on all displays");
+                println!("         --exclude: skip a display, selected like --display,");
+                println!("                    can be repeated");
+                println!("   --exclude-model: skip displays whose model name contains MODEL");
+                println!(" --require-feature: only use displays whose capabilities list VCP");
+                println!("                    feature CODE, given in hex (e.g. 0x60)");
                 println!("  -l,       --list: list all detected displays and metadata");
-                println!("  -v,    --version: get the program version");
+                println!("      --list-buses: list the i2c buses displays may be on");
+                println!("             --bus: use the display on i2c bus N or /dev/i2c-N");
+                println!("                    directly instead of finding displays");
+                println!("     --interactive: ask which display to use when several are");
+                println!("                    selected");
+                println!("      --brightness: also show the current brightness with --list");
+                println!("            --sort: order --list by KEY: index (default), model or");
+                println!("                    serial");
+                println!("     --date-format: show manufacture dates with --list as the week");
+                println!("                    (default) or an approximate date");
+                println!("          --filter: only show displays whose model contains MODEL");
+                println!("                    with --list");
+                println!(" --only-brightness: only show displays which support brightness with");
+                println!("                    --list, checking their capabilities");
+                println!("   --show-features: show which of brightness, contrast, input,");
+                println!("                    volume and power each display supports with --list");
+                println!("  -V,    --version: get the program version, with --json also the");
+                println!("                    commit and build date if known");
+                println!("  -q,      --quiet: only print errors and the output asked for");
+                println!("  -v,    --verbose: also print how displays were found and retries,");
+                println!("                    twice to print every DDC command and its timing");
                 println!("  -h,       --help: print this help message");
-                println!("             --get: get the current brightness");
-                println!("             --set: set brightness to NUM percent");
+                println!("             --get: get the current brightness, or with FEATURES the");
+                println!("                    value of each in a comma separated list of");
+                println!("                    brightness, contrast, input, power, color-temp,");
+                println!("                    red, green, blue and volume");
+                println!("          --format: print --get output as FORMAT: text (default),");
+                println!("                    value for just the number, or bar for a gauge");
+                println!(
+                    "             --set: set brightness to NUM percent, - reads NUM from stdin"
+                );
+                println!("                    and env:NAME from the environment variable NAME");
                 println!("             --inc: increase brightness by NUM percent");
                 println!("             --dec: decrease brightness by NUM percent");
+                println!("            --step: how much --inc and --dec change brightness by");
+                println!("                    when NUM is left out (default 5)");
+                println!("      --perceptual: make --inc and --dec steps look even, smaller when");
+                println!("                    dim and larger when bright");
+                println!("         --inc-pct: increase brightness by NUM percent of its current");
+                println!("                    value, always by at least one step");
+                println!("         --dec-pct: decrease brightness by NUM percent of its current");
+                println!("                    value, always by at least one step");
+                println!("          --toggle: set brightness to HIGH if it is closer to LOW,");
+                println!("                    otherwise set it to LOW");
+                println!("        --equalize: set every display to their average brightness,");
+                println!("                    or to NUM percent if given");
+                println!("       --sync-from: set the selected displays to the brightness of");
+                println!("                    display NUM");
+                println!("     --relative-to: the same as --sync-from, to be used with --offset");
+                println!("          --offset: with --relative-to, set the selected displays NUM");
+                println!("                    percent above (or below if negative) the display");
+                println!("    --contrast-set: set contrast to NUM percent");
+                println!("    --contrast-inc: increase contrast by NUM percent");
+                println!("    --contrast-dec: decrease contrast by NUM percent");
+                println!("      --volume-set: set volume to NUM percent");
+                println!("      --volume-inc: increase volume by NUM percent");
+                println!("      --volume-dec: decrease volume by NUM percent");
+                println!("             --red: set the red video gain to NUM percent");
+                println!("           --green: set the green video gain to NUM percent");
+                println!("            --blue: set the blue video gain to NUM percent,");
+                println!("                    any of the three can be combined");
+                println!("           --input: switch to input NAME (e.g. hdmi1, dp1, usbc)");
+                println!("                    or a raw MCCS input source value");
+                println!("        --identify: flash the brightness of the selected displays");
+                println!("                    to find them, then restore it");
+                println!("    --capabilities: print the capabilities string and supported");
+                println!("                    features of the selected displays");
+                println!("        --features: print a table of the value and maximum of every");
+                println!("                    supported feature of the selected displays");
+                println!("      --color-temp: select colour preset PRESET, e.g. 6500k, srgb");
+                println!("          --preset: set the brightness, contrast and colour preset");
+                println!("                    given by preset NAME in the config");
+                println!("           --power: switch power to STATE: on, standby, suspend or off");
+                println!("         --vcp-get: print the raw value and maximum of VCP feature");
+                println!("                    CODE, given in hex (e.g. 0x60)");
+                println!("         --vcp-set: write the raw VALUE to VCP feature CODE");
+                println!("           --reset: restore the factory defaults of the selected");
+                println!("                    displays, after asking for confirmation");
+                println!("  -y,        --yes: reset without asking for confirmation, and make");
+                println!("                    changes larger than --confirm without asking");
+                println!("         --confirm: ask before changing brightness by more than NUM");
+                println!("                    percent");
+                println!("    --save-profile: save the brightness of each display as profile NAME");
+                println!("    --load-profile: restore the brightness saved in profile NAME");
+                println!(" --set-relative-to: restore profile NAME with a relative change such");
+                println!("                    as +10 made to each saved brightness");
+                println!("            --undo: restore the brightness from before the last change");
+                println!("            --auto: load profile day or night, depending on the time");
+                println!("        --location: with --auto, work out sunrise and sunset for");
+                println!("                    LAT,LON in degrees north and east");
+                println!("         --sunrise: with --auto, the time day starts, as HH:MM");
+                println!("          --sunset: with --auto, the time night starts, as HH:MM");
+                println!("           --watch: print the brightness whenever it changes, until");
+                println!("                    interrupted with Ctrl-C");
+                println!("        --interval: how often --watch polls, in ms (default 1000)");
+                println!("            --json: print --list and --get output as JSON");
+                println!("             --raw: read and change brightness in the display's own");
+                println!("                    units instead of as a percentage");
+                println!("            --fade: gradually change to the new value over MS");
+                println!("                    milliseconds, following EASE: linear (default),");
+                println!("                    ease-in, ease-out or ease-in-out");
+                println!("         --retries: retry failed DDC commands N times (default 2)");
+                println!("           --delay: wait MS milliseconds between DDC commands instead");
+                println!("                    of the DDC/CI default, too low causes errors");
+                println!("         --timeout: wait MS milliseconds for displays to reply to");
+                println!("                    reads (default 40)");
+                println!("        --no-cache: don't read or write the display cache");
+                println!("   --refresh-cache: enumerate displays again and rebuild the cache");
+                println!("       --no-dedupe: keep displays found more than once, such as on");
+                println!("                    two i2c buses");
+                println!("          --notify: show a desktop notification of the new value");
+                println!("                    after a change, using notify-send");
+                println!("      --wake-first: switch displays on and wait MS milliseconds");
+                println!("                    (default 1000) before acting on them");
+                println!("       --off-after: switch displays off SECONDS after acting on them,");
+                println!("                    unless cancelled with Ctrl-C");
+                println!("       --fail-fast: stop at the first display which fails, displays");
+                println!("                    are then changed one at a time");
+                println!(" --max-concurrency: act on at most N displays at once (default 4)");
+                println!("         --no-wait: fail rather than wait when another invocation is");
+                println!("                    changing displays");
+                println!("          --script: run each line of FILE as further arguments, against");
+                println!("                    displays which are only found once");
+                println!("         --dry-run: print what would be changed without writing");
+                println!("          --verify: read features back after changing them and warn");
+                println!("                    if the display reports a different value");
+                println!("          --timing: print how long enumeration and each read and");
+                println!("                    write took when finished");
+                println!("             --min: never set brightness below NUM percent");
+                println!("             --max: never set brightness above NUM percent");
+                println!("           --force: let relative decreases go below the safe_floor");
+                println!("                    configured for a display");
+                println!("       --exit-code: with --get, exit with the brightness of the first");
+                println!("                    selected display, or 255 on failure");
+                println!("          --daemon: keep displays open and apply relative changes");
+                println!("                    sent by other invocations, coalescing rapid ones");
+                println!("           --color: colour errors: auto, always or never");
+                println!("                    (default auto, honours NO_COLOR)");
+                println!("          --config: read default options from PATH instead of");
+                println!("                    $XDG_CONFIG_HOME/ddc-brightness-ctl/config.toml");
+                println!("       --no-config: don't read the config file");
+                println!();
+                println!(
+                    "A bare NUM, +NUM or -NUM is short for --set=NUM, --inc=NUM or --dec=NUM."
+                );
                 std::process::exit(0);
             }
-            _ => return Err(arg.unexpected()),
+            _ => return Err(arg.unexpected().into()),
+        }
+    }
+
+    // handled once every argument is parsed so that --json can come after it
+    if version {
+        print_version(json);
+        std::process::exit(0);
+    }
+
+    let config = match (use_config, config_path) {
+        (false, _) => Config::default(),
+        (true, Some(path)) => config::load_from(&path).map_err(Error::Config)?,
+        (true, None) => config::load().map_err(Error::Config)?,
+    };
+    if let Some(name) = preset {
+        action = Action::Preset(find_preset(&config, &name)?);
+    }
+    // options given on the command line take precedence over the config
+    if display.is_empty() {
+        display = config_value(
+            "display",
+            config.display.as_deref(),
+            DisplaySelector::parse_list,
+        )?
+        .unwrap_or_default();
+    }
+    DisplaySelector::resolve_aliases(&mut display, &config);
+    DisplaySelector::resolve_aliases(&mut exclude, &config);
+    exclude.extend(exclude_models);
+    if auto {
+        let location = match location {
+            Some(location) => Some(location),
+            None => config_value("location", config.location.as_deref(), parse_location)?,
+        };
+        let sunrise = match sunrise {
+            Some(sunrise) => Some(sunrise),
+            None => config_value("sunrise", config.sunrise.as_deref(), parse_time)?,
+        };
+        let sunset = match sunset {
+            Some(sunset) => Some(sunset),
+            None => config_value("sunset", config.sunset.as_deref(), parse_time)?,
+        };
+
+        let schedule = match (location, sunrise, sunset) {
+            (Some((latitude, longitude)), _, _) => Schedule::Location {
+                latitude,
+                longitude,
+            },
+            (None, Some(sunrise), Some(sunset)) => Schedule::Times { sunrise, sunset },
+            _ => {
+                let msg = "--auto needs --location, or both --sunrise and --sunset";
+                return Err(lexopt::Error::Custom(msg.into()).into());
+            }
+        };
+        profile = Some(ProfileCommand::Auto(schedule));
+    }
+    // raw values can only be checked against the display's maximum once it has been read,
+    // anything past it is clamped then
+    if !raw
+        && let Action::Change(feature, change) = action
+        && let Err(msg) = change.check_range(feature)
+    {
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    if raw && percent_given {
+        let msg = "--raw values aren't percentages, so can't be written with a %";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    if json && format != GetFormat::Text {
+        let msg = "--format can't be used with --json";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    if let Action::Get(features) = action
+        && !features.is_brightness()
+        && (format != GetFormat::Text || exit_with_value)
+    {
+        let msg = "--format and --exit-code only work with --get of brightness alone";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    // these work out percentages across displays, which raw values can't be compared as
+    if raw
+        && (equalize.is_some()
+            || profile.is_some()
+            || relative_to.is_some()
+            || matches!(action, Action::SyncFrom(..)))
+    {
+        let msg = "--raw can't be used with --equalize, --sync-from or profiles";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    // a later action replaces a pending step, so it's only applied if nothing did
+    if let (Some(direction), Action::Change(Feature::Luminance, BrightnessChange::Relative(0))) =
+        (step_direction, action)
+    {
+        let step = step.or(config.step).unwrap_or(DEFAULT_STEP);
+        let offset = i16::try_from(step).unwrap_or(i16::MAX) * direction;
+        action = Action::Change(Feature::Luminance, BrightnessChange::Relative(offset));
+    }
+    if max_concurrency == 0 {
+        let msg = "--max-concurrency must be at least 1";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    // polling without a pause would keep the i2c bus busy for nothing
+    if interval.is_zero() {
+        let msg = "--interval must be at least 1";
+        return Err(lexopt::Error::Custom(msg.into()).into());
+    }
+    if let Some(offset) = offset {
+        let Action::SyncFrom(source, _) = action else {
+            let msg = "--offset needs a display to be relative to, given with --relative-to";
+            return Err(lexopt::Error::Custom(msg.into()).into());
+        };
+        if synced_by_sync_from {
+            let msg = "--offset can't be used with --sync-from, use --relative-to instead";
+            return Err(lexopt::Error::Custom(msg.into()).into());
         }
+        action = Action::SyncFrom(source, offset);
     }
+    if let Some(name) = relative_to {
+        let Action::Change(Feature::Luminance, BrightnessChange::Relative(offset)) = action else {
+            let msg = "--set-relative-to needs a change to make, such as +10 or --dec=5";
+            return Err(lexopt::Error::Custom(msg.into()).into());
+        };
+        profile = Some(ProfileCommand::RelativeTo(name, offset));
+    }
+    let color = match color {
+        Some(color) => color,
+        None => config_value("color", config.color.as_deref(), ColorMode::parse)?
+            .unwrap_or(ColorMode::Auto),
+    };
+    let proceed_unattended = config_value(
+        "confirm_unattended",
+        config.confirm_unattended.as_deref(),
+        parse_unattended,
+    )?
+    .unwrap_or(true);
+    let confirm = confirm_delta
+        .or(config.confirm_delta)
+        .filter(|_| !yes)
+        .map(|delta| ConfirmJumps {
+            delta,
+            proceed_unattended,
+        });
 
     Ok(Args {
         action,
         display,
+        exclude,
+        require_features,
         list,
+        list_brightness,
+        list_sort,
+        date_format,
+        list_filter,
+        list_only_brightness,
+        list_features,
+        list_buses,
+        bus,
+        equalize,
+        profile,
+        watch: watch.then_some(interval),
+        cache,
+        no_dedupe,
+        fail_fast,
+        max_concurrency,
+        no_wait,
+        exit_with_value,
+        daemon,
+        yes,
+        interactive,
+        notify,
+        wake_first,
+        off_after,
+        verbosity,
+        min,
+        max,
+        force,
+        script,
+        options: Options {
+            json,
+            fade,
+            retries: retries.or(config.retries).unwrap_or(DEFAULT_RETRIES),
+            delay: delay.or(config.delay),
+            timeout,
+            color: color.enabled(),
+            dry_run,
+            limits: Limits::default(),
+            raw,
+            perceptual,
+            format,
+            confirm,
+            verify,
+        },
+        config,
     })
 }
 
 fn main() -> ExitCode {
+    let start = Instant::now();
+    let code = run();
+
+    if timing::enabled() {
+        // on stderr, so that it can be asked for alongside --json
+        eprintln!("Timing:");
+        for (what, elapsed) in timing::take() {
+            eprintln!("  {what}: {elapsed:?}");
+        }
+        eprintln!("  total: {:?}", start.elapsed());
+    }
+
+    code
+}
+
+fn run() -> ExitCode {
+    // looked for before parsing, so that invalid arguments fail with the right status too
+    let exit_code = env::args_os().skip(1).any(|arg| arg == "--exit-code");
+    EXIT_WITH_VALUE.store(exit_code, Ordering::Relaxed);
+    let args = match parse_args(lexopt::Parser::from_env()) {
+        Ok(args) => args,
+        Err(e) => {
+            let msg = render_error(&e, None);
+            error(ColorMode::Auto.enabled(), &msg);
+            return failure();
+        }
+    };
     let Args {
         action,
-        display,
+        ref display,
+        ref exclude,
+        ref require_features,
         list,
-    } = match parse_args() {
-        Ok(args) => args,
-        Err(e) => {
-            eprintln!("{RED}Failed to parse arguments: {e}{RESET}");
-            return ExitCode::FAILURE;
+        list_brightness,
+        list_sort,
+        date_format,
+        ref list_filter,
+        list_only_brightness,
+        list_features,
+        list_buses,
+        ref bus,
+        equalize,
+        ref profile,
+        watch,
+        cache,
+        no_dedupe,
+        daemon,
+        interactive,
+        notify,
+        wake_first,
+        off_after,
+        verbosity,
+        min,
+        max,
+        force,
+        ref script,
+        ref config,
+        options,
+        ..
+    } = args;
+    log::set_level(verbosity);
+
+    if action.is_noop()
+        && !list
+        && !list_buses
+        && equalize.is_none()
+        && profile.is_none()
+        && watch.is_none()
+        && !daemon
+        && script.is_none()
+    {
+        return ExitCode::SUCCESS;
+    }
+
+    // plain relative changes are handed to a running daemon, which coalesces them
+    if let Action::Change(feature, BrightnessChange::Relative(offset)) = action
+        && !daemon
+        && !list
+        && equalize.is_none()
+        && profile.is_none()
+        && watch.is_none()
+        && !interactive
+        && exclude.is_empty()
+        && require_features.is_empty()
+        && options.fade.is_none()
+        && options.timeout.is_none()
+        && !options.raw
+        && !options.perceptual
+        && !options.dry_run
+        && !options.verify
+        && !options.json
+        && !notify
+        && wake_first.is_none()
+        && off_after.is_none()
+        && bus.is_none()
+        && !no_dedupe
+        && script.is_none()
+        && min.is_none()
+        && max.is_none()
+        && !force
+        // the daemon can't ask, but steps this small never need to
+        && options
+            .confirm
+            .is_none_or(|confirm| offset.unsigned_abs() <= confirm.delta)
+    {
+        let request = Request {
+            feature,
+            offset,
+            display: (!display.is_empty()).then(|| DisplaySelector::format_list(display)),
+        };
+        match daemon::forward(&request) {
+            Ok(Some((out, ok))) => {
+                out.flush();
+                return if ok { ExitCode::SUCCESS } else { failure() };
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let msg = format!("Failed to talk to the daemon: {e}");
+                error(options.color, &msg);
+                return failure();
+            }
         }
+    }
+
+    let per_display = |disp: &Monitor, options: Options| {
+        per_display_options(config, disp, min, max, force, options)
+    };
+
+    if daemon {
+        return run_daemon(cache, no_dedupe, options, &per_display);
+    }
+
+    if list_buses {
+        return print_buses(options);
+    }
+
+    let mut displays = match bus {
+        Some(bus) => match timing::time(
+            None,
+            || format!("open {}", bus.display()),
+            || i2c::open(bus),
+        ) {
+            Ok(display) => vec![Monitor::from(display)],
+            Err(e) => {
+                let mut msg = format!("Failed to open {}: {e}", bus.display());
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+                ) && let Some(problem) = i2c::access_problem()
+                {
+                    msg.push_str(&format!(", {}", problem.advice()));
+                }
+                error(options.color, &msg);
+                return failure();
+            }
+        },
+        None => enumerate_displays(cache, no_dedupe, options),
+    };
+
+    // the displays --list shows, in the order it shows them
+    let mut listed = if list {
+        list_order(&displays, list_sort, list_filter.as_deref())
+    } else {
+        Vec::new()
     };
+    // checking costs a capabilities read per display, so it's opt in
+    let use_cache = cache != CacheMode::Disabled;
+    if list_only_brightness {
+        listed.retain(|&i| supports(&mut displays[i], Feature::Luminance, use_cache));
+    }
+
+    // describing a display reads its brightness once to see whether it answers
+    let infos: Vec<_> = listed
+        .iter()
+        .map(|&i| DisplayInfo::read(i, &mut displays[i]))
+        .collect();
+    let brightness: Vec<_> = infos
+        .iter()
+        .map(|info| info.brightness.filter(|_| list_brightness))
+        .collect();
+    // displays which don't answer DDC/CI would only time out reading their capabilities
+    let features: Vec<Vec<_>> = infos
+        .iter()
+        .map(|info| {
+            let disp = &mut displays[info.index];
+            if !list_features || matches!(disp.backend, Backend::Ddc(_)) && !info.ddc {
+                return Vec::new();
+            }
+            let supported = supported(disp, &LIST_FEATURES.map(|(_, feature)| feature), use_cache);
+            LIST_FEATURES
+                .iter()
+                .filter(|(_, feature)| supported.contains(feature))
+                .map(|&(name, _)| name)
+                .collect()
+        })
+        .collect();
 
-    if action.is_noop() && !list {
+    if list && options.json {
+        let entries = infos
+            .iter()
+            .zip(&brightness)
+            .zip(&features)
+            .map(|((info, brightness), features)| {
+                let mut json = info.to_json();
+                if let Json::Object(fields) = &mut json {
+                    if list_brightness {
+                        fields.push(("brightness", (*brightness).into()));
+                    }
+                    if list_features {
+                        let names = features.iter().map(|&name| name.into()).collect();
+                        fields.push(("features", Json::Array(names)));
+                    }
+                }
+                json
+            })
+            .collect();
+        println!("{}", Json::Array(entries));
         return ExitCode::SUCCESS;
     }
 
-    println!("Querying display info... (~1-2 seconds)");
-    let mut displays = Display::enumerate();
+    if list && displays.is_empty() {
+        println!("No displays detected");
+        return ExitCode::SUCCESS;
+    }
 
     if list {
         println!("Detected displays:");
-        for (i, disp) in displays.iter().enumerate() {
+        for ((info, brightness), features) in infos.iter().zip(brightness).zip(&features) {
+            let (i, disp, ddc) = (info.index, &displays[info.index], info.ddc);
+            let brightness = match brightness {
+                Some(percent) => format!(", brightness {percent}%"),
+                None if list_brightness => ", brightness ?".to_string(),
+                None => String::new(),
+            };
+            let backend = match (&disp.backend, ddc) {
+                (Backend::Ddc(_), true) => "DDC: yes".to_string(),
+                (Backend::Ddc(_), false) => "DDC: no".to_string(),
+                (Backend::Backlight(backlight), _) => format!("via backlight {}", backlight.name()),
+                (Backend::Custom(backend), _) => format!("via {}", backend.name()),
+            };
+            let features = if list_features {
+                format!(" [{}]", features.join(","))
+            } else {
+                String::new()
+            };
             println!(
-                "  - [{i}]: {} - ({}:{}:{}), manufactured week {} of {}",
-                disp.info.model_name.as_deref().unwrap_or("Unknown Model"),
-                disp.info.manufacturer_id.as_deref().unwrap_or("???"),
-                disp.info
-                    .model_id
-                    .map(|num| format!("{num:04X}"))
-                    .as_deref()
-                    .unwrap_or("????"),
-                disp.info
-                    .serial
-                    .map(|num| format!("{num:08X}"))
-                    .as_deref()
-                    .unwrap_or("????????"),
-                disp.info
-                    .manufacture_week
-                    .map(|num| format!("{num}"))
-                    .as_deref()
-                    .unwrap_or("??"),
-                disp.info
-                    .manufacture_year
-                    .map(|num| format!("{}", 1990 + num as u16))
-                    .as_deref()
-                    .unwrap_or("????"),
+                "  - {}, {backend}{brightness}{features}",
+                list_entry(i, disp, date_format)
             );
         }
 
         return ExitCode::SUCCESS;
     }
 
-    if let Some(n) = display {
-        if let Some(disp) = displays.get_mut(n) {
-            return action.execute(disp, n);
+    // doing nothing successfully would hide that DDC isn't working at all
+    if displays.is_empty() {
+        let msg = match i2c::access_problem() {
+            Some(problem) => format!("No DDC-capable displays found: {}", problem.advice()),
+            None => "No DDC-capable displays found, check that they are switched on and have \
+                     DDC/CI enabled in their menus"
+                .to_string(),
+        };
+        error(options.color, &msg);
+        return failure();
+    }
+
+    if let Some(path) = script {
+        return run_script(path, &mut displays, &args);
+    }
+
+    apply(args, &mut displays)
+}
+
+fn print_buses(options: Options) -> ExitCode {
+    let buses = match i2c::buses() {
+        Ok(buses) => buses,
+        Err(e) => {
+            error(options.color, &format!("Failed to list i2c buses: {e}"));
+            return failure();
+        }
+    };
+
+    if options.json {
+        let entries = buses
+            .iter()
+            .map(|bus| {
+                Json::Object(vec![
+                    ("path", bus.path.to_string_lossy().as_ref().into()),
+                    ("name", bus.name.as_deref().into()),
+                ])
+            })
+            .collect();
+        println!("{}", Json::Array(entries));
+    } else if buses.is_empty() {
+        println!("No i2c buses found, load the i2c-dev module with `sudo modprobe i2c-dev`");
+    } else {
+        println!("I2C buses:");
+        for bus in &buses {
+            match &bus.name {
+                Some(name) => println!("  - {} ({name})", bus.path.display()),
+                None => println!("  - {}", bus.path.display()),
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+// one notification for the whole run, a single value when every display ended up at the same one
+fn notify_change(feature: Feature, changed: &[(usize, u16)], options: Options) {
+    let Some(&(_, first)) = changed.first() else {
+        return;
+    };
+
+    let unit = if options.raw { "" } else { "%" };
+    let name = feature.name();
+    let summary = format!("{}{}", name[..1].to_uppercase(), &name[1..]);
+    if changed.iter().all(|&(_, value)| value == first) {
+        let percent = (!options.raw).then_some(first);
+        notify::send(&summary, &format!("{first}{unit}"), percent);
+    } else {
+        let values: Vec<_> = changed
+            .iter()
+            .map(|(i, value)| format!("display {i}: {value}{unit}"))
+            .collect();
+        notify::send(&summary, &values.join(", "), None);
+    }
+}
+
+// runs each line of a script as if its arguments followed those on the command line, against
+// displays which are only enumerated once
+fn run_script(path: &Path, displays: &mut [Monitor], args: &Args) -> ExitCode {
+    let color = args.options.color;
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            error(
+                color,
+                &format!("Failed to read script {}: {e}", path.display()),
+            );
+            return failure();
+        }
+    };
+
+    // the arguments every line starts from, leaving out the script itself
+    let mut base = Vec::new();
+    let mut os_args = env::args_os().skip(1);
+    while let Some(arg) = os_args.next() {
+        if arg == "--script" {
+            os_args.next();
+        } else if !arg.to_str().is_some_and(|arg| arg.starts_with("--script=")) {
+            base.push(arg);
+        }
+    }
+
+    let lines: Vec<_> = contents
+        .lines()
+        .enumerate()
+        .map(|(line_no, line)| (line_no + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let mut attempted = 0;
+    let mut failed = Vec::new();
+    for &(line_no, line) in &lines {
+        attempted += 1;
+        let parsed = split_words(line)
+            .map_err(|e| Error::ParseArgs(lexopt::Error::Custom(e.into())))
+            .and_then(|words| {
+                let words = words.into_iter().map(OsString::from);
+                parse_args(lexopt::Parser::from_args(base.iter().cloned().chain(words)))
+            });
+        let ok = match parsed {
+            Ok(line_args)
+                if line_args.list
+                    || line_args.daemon
+                    || line_args.watch.is_some()
+                    || line_args.script.is_some() =>
+            {
+                let msg = format!(
+                    "Line {line_no}: --list, --watch, --daemon and --script can't be used in a script"
+                );
+                error(color, &msg);
+                false
+            }
+            Ok(line_args) => apply(line_args, displays) == ExitCode::SUCCESS,
+            Err(e) => {
+                error(
+                    color,
+                    &format!("Line {line_no}: {}", render_error(&e, None)),
+                );
+                false
+            }
+        };
+
+        if ok {
+            log::info(format_args!("Line {line_no} done"));
         } else {
-            eprintln!("{RED}No display {n}{RESET}");
-            return ExitCode::FAILURE;
+            error(color, &format!("Line {line_no} failed"));
+            failed.push(line_no);
+            if args.fail_fast || signal::interrupted() {
+                break;
+            }
         }
     }
 
-    let mut exit_code = ExitCode::SUCCESS;
-    for (i, mut disp) in displays.into_iter().enumerate() {
-        if action.execute(&mut disp, i) == ExitCode::FAILURE {
-            exit_code = ExitCode::FAILURE;
+    let total = lines.len();
+    let mut summary = format!("{}/{total} script lines done", attempted - failed.len());
+    if !failed.is_empty() {
+        let line_nos: Vec<_> = failed.iter().map(ToString::to_string).collect();
+        summary.push_str(&format!(
+            ", {} failed: {}",
+            failed.len(),
+            line_nos.join(", ")
+        ));
+    }
+    if attempted < total {
+        summary.push_str(&format!(", {} skipped", total - attempted));
+    }
+
+    if failed.is_empty() {
+        log::info(format_args!("{summary}"));
+        ExitCode::SUCCESS
+    } else if failed.len() < attempted {
+        error(color, &summary);
+        partial_failure()
+    } else {
+        error(color, &summary);
+        failure()
+    }
+}
+
+// splits a line of a script into arguments at whitespace, quoting with ' or " keeps whitespace
+// in an argument
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.get_or_insert_default().push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            None => word.get_or_insert_default().push(c),
         }
     }
+    if quote.is_some() {
+        return Err(format!("unterminated quote in '{line}'"));
+    }
+    words.extend(word);
 
-    exit_code
+    Ok(words)
+}
+
+// selects displays and carries out the action on them, the rest of what running with `args`
+// does once the displays are known
+fn apply(args: Args, displays: &mut [Monitor]) -> ExitCode {
+    let Args {
+        action,
+        display,
+        exclude,
+        require_features,
+        equalize,
+        profile,
+        watch,
+        cache,
+        fail_fast,
+        max_concurrency,
+        no_wait,
+        exit_with_value,
+        yes,
+        interactive,
+        notify,
+        wake_first,
+        off_after,
+        date_format,
+        min,
+        max,
+        force,
+        config,
+        options,
+        ..
+    } = args;
+
+    let per_display = |disp: &Monitor, options: Options| {
+        per_display_options(&config, disp, min, max, force, options)
+    };
+
+    let writes = match &profile {
+        Some(ProfileCommand::Save(_)) => false,
+        Some(_) => true,
+        None => !action.is_read_only() || equalize.is_some(),
+    };
+    let _lock = if writes && watch.is_none() && !options.dry_run {
+        match take_lock(!no_wait, options) {
+            Ok(lock) => lock,
+            Err(code) => return code,
+        }
+    } else {
+        None
+    };
+
+    // the source is read before displays are selected, as it needn't be one of them
+    let synced = match action {
+        Action::SyncFrom(source, offset) => {
+            let Some(disp) = displays.get_mut(source) else {
+                let e = Error::NoSuchDisplay(source.to_string());
+                error(options.color, &render_error(&e, None));
+                return failure();
+            };
+            match read_brightness(disp, per_display(disp, options)) {
+                Ok(brightness) if offset == 0 => {
+                    if !options.json {
+                        let disp = display_name(source, disp);
+                        log::info(format_args!(
+                            "Syncing brightness to {disp} at {brightness}%"
+                        ));
+                    }
+                    Some(brightness)
+                }
+                Ok(brightness) => {
+                    if !options.json {
+                        let disp = display_name(source, disp);
+                        log::info(format_args!(
+                            "Setting brightness {offset:+}% from {disp} at {brightness}%"
+                        ));
+                    }
+                    Some(brightness.saturating_add_signed(offset).min(100))
+                }
+                Err(e) => {
+                    let msg = render_error(&e, Some(&display_name(source, disp)));
+                    error(options.color, &msg);
+                    return failure();
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let connected: Vec<_> = displays
+        .iter()
+        .filter_map(|disp| disp.info.serial)
+        .collect();
+    let mut displays: Vec<_> = displays.iter_mut().enumerate().collect();
+    if !display.is_empty() {
+        let models: Vec<_> = displays
+            .iter()
+            .map(|(_, disp)| disp.info.model_name.as_deref().unwrap_or("Unknown Model"))
+            .map(str::to_string)
+            .collect();
+
+        // every selector has to match before anything is changed, so that a typo in a list
+        // doesn't go unnoticed
+        let unmatched = display
+            .iter()
+            .find(|selector| !displays.iter().any(|(i, disp)| selector.matches(*i, disp)));
+        if let Some(selector) = unmatched {
+            let selection = match selector {
+                DisplaySelector::Index(n) => n.to_string(),
+                DisplaySelector::Serial(serial) => format!("with serial {serial:08X}"),
+                DisplaySelector::Alias { name, serial } => {
+                    format!("with serial {serial:08X}, which alias {name} refers to")
+                }
+                DisplaySelector::Connector { name, bus } => {
+                    format!("on connector {name} ({})", bus.display())
+                }
+                DisplaySelector::Model(name) => format!(
+                    "model matching \"{name}\", available models: {}",
+                    models.join(", ")
+                ),
+            };
+            let e = Error::NoSuchDisplay(selection);
+            error(options.color, &render_error(&e, None));
+            return failure();
+        }
+
+        displays.retain(|(i, disp)| display.iter().any(|selector| selector.matches(*i, disp)));
+    }
+
+    displays.retain(|(i, disp)| !exclude.iter().any(|selector| selector.matches(*i, disp)));
+    if let Action::SyncFrom(source, _) = action {
+        displays.retain(|(i, _)| *i != source);
+    }
+
+    // displays which are known not to support the feature are only tried when asked for by name
+    if display.is_empty()
+        && cache != CacheMode::Disabled
+        && let Some(feature) = action.feature()
+    {
+        displays.retain(|(i, disp)| {
+            let supported = !cache::unsupported_features(&disp.info).contains(&feature.code());
+            if !supported && !options.json {
+                log::info(format_args!(
+                    "Skipping {}, which doesn't support {} (select it with -d to try anyway)",
+                    display_name(*i, disp),
+                    feature.name()
+                ));
+            }
+            supported
+        });
+    }
+
+    // this costs a capabilities read per display unless they are cached, and the displays left
+    // out aren't errors
+    if !require_features.is_empty() {
+        let use_cache = cache != CacheMode::Disabled;
+        displays.retain_mut(|(i, disp)| {
+            let missing = require_features
+                .iter()
+                .find(|&&code| !supports(disp, Feature::from_code(code), use_cache));
+            if let Some(code) = missing {
+                log::debug(format_args!(
+                    "Skipping {}, which doesn't list VCP 0x{code:02X} in its capabilities",
+                    display_name(*i, disp)
+                ));
+            }
+            missing.is_none()
+        });
+    }
+
+    if interactive && displays.len() > 1 {
+        match pick_display(&displays, date_format) {
+            Ok(picked) => displays.retain(|(i, _)| *i == picked),
+            Err(e) => {
+                error(options.color, &e);
+                return failure();
+            }
+        }
+    }
+
+    // identifying and fading restore the original brightness when interrupted rather than
+    // exiting, any displays not yet started are then skipped, and --off-after is cancelled
+    if matches!(action, Action::Identify) || options.fade.is_some() || off_after.is_some() {
+        signal::catch_interrupt();
+    }
+
+    if let Some(interval) = watch {
+        watch_displays(&mut displays, interval, per_display, options);
+        return ExitCode::SUCCESS;
+    }
+
+    let undo = matches!(profile, Some(ProfileCommand::Undo));
+    let offset = match profile {
+        Some(ProfileCommand::RelativeTo(_, offset)) => offset,
+        _ => 0,
+    };
+    // the profile to restore, and where it came from for messages
+    let profile = match profile {
+        Some(ProfileCommand::Save(name)) => {
+            return save_profile(&name, &mut displays, max_concurrency, options);
+        }
+        Some(ProfileCommand::Load(name) | ProfileCommand::RelativeTo(name, _)) => {
+            match profile::load(&name) {
+                Ok(profile) => Some((format!("profile {name}"), profile)),
+                Err(e) => {
+                    let msg = format!("Failed to load profile {name}: {e}");
+                    error(options.color, &msg);
+                    return failure();
+                }
+            }
+        }
+        Some(ProfileCommand::Auto(schedule)) => {
+            let name = schedule.period(SystemTime::now()).name();
+            if !options.json {
+                log::info(format_args!("It's {name}, loading profile {name}"));
+            }
+            match profile::load(name) {
+                Ok(profile) => Some((format!("profile {name}"), profile)),
+                Err(e) => {
+                    let msg = format!("Failed to load profile {name}: {e}");
+                    error(options.color, &msg);
+                    return failure();
+                }
+            }
+        }
+        Some(ProfileCommand::Undo) => match undo::load() {
+            Ok(state) => Some(("the undo history".to_string(), state)),
+            Err(e) => {
+                error(options.color, &format!("Failed to load undo history: {e}"));
+                return failure();
+            }
+        },
+        None => None,
+    };
+
+    // the undo history keeps every display ever changed, so missing ones are expected there;
+    // elsewhere they're only warned about, so that a profile still applies when undocked
+    let mut missing = 0;
+    if let Some((name, profile)) = &profile
+        && !undo
+    {
+        for saved in &profile.displays {
+            if !connected.contains(&saved.serial) {
+                let model = saved.model.as_deref().unwrap_or("Unknown Model");
+                let msg = format!(
+                    "Display {model} with serial {:08X} from {name} is not connected, skipping",
+                    saved.serial
+                );
+                warn(options.color, &msg);
+                missing += 1;
+            }
+        }
+    }
+
+    let action = match synced {
+        Some(brightness) => {
+            Action::Change(Feature::Luminance, BrightnessChange::Absolute(brightness))
+        }
+        None => action,
+    };
+    let action = match equalize {
+        None => action,
+        Some(Some(target)) => {
+            Action::Change(Feature::Luminance, BrightnessChange::Absolute(target))
+        }
+        Some(None) => match average_brightness(&mut displays, per_display, options) {
+            Some(average) => {
+                Action::Change(Feature::Luminance, BrightnessChange::Absolute(average))
+            }
+            None => {
+                let msg = "Couldn't read the brightness of any display to equalize";
+                error(options.color, msg);
+                return failure();
+            }
+        },
+    };
+
+    // a profile replaces the action with the brightness saved for each display, plus the offset
+    // for --set-relative-to
+    let jobs: Vec<_> = displays
+        .iter_mut()
+        .filter_map(|(i, disp)| {
+            let Some((name, profile)) = &profile else {
+                return Some((*i, disp, action));
+            };
+
+            let saved = disp.info.serial.and_then(|serial| profile.get(serial));
+            match saved.and_then(|saved| saved.brightness) {
+                Some(brightness) => {
+                    let brightness = brightness.saturating_add_signed(offset).min(100);
+                    let change = BrightnessChange::Absolute(brightness);
+                    Some((*i, disp, Action::Change(Feature::Luminance, change)))
+                }
+                None => {
+                    let msg = format!(
+                        "No brightness saved for {} in {name}, skipping",
+                        display_name(*i, disp)
+                    );
+                    warn(options.color, &msg);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    if matches!(action, Action::Reset) && !yes && !options.dry_run && !jobs.is_empty() {
+        let names: Vec<_> = jobs
+            .iter()
+            .map(|(i, disp, _)| display_name(*i, disp))
+            .collect();
+        if !confirm(&format!("Reset {} to factory defaults?", names.join(", "))) {
+            error(
+                options.color,
+                "Not resetting, pass --yes to skip this prompt",
+            );
+            return failure();
+        }
+    }
+
+    let execute = |i: usize, disp: &mut Monitor, action: Action| {
+        let options = per_display(disp, options);
+        let mut out = Output::new(options);
+        // a display coming out of standby ignores commands until it is ready
+        if let Some(wait) = wake_first
+            && !options.dry_run
+        {
+            out.info(format_args!("Waking {}", display_name(i, disp)));
+            if let Err(e) = wake(disp, options) {
+                let msg = render_error(&e, Some(&display_name(i, disp)));
+                let msg = format!("{msg}, it may be switched off rather than in standby");
+                out.eprintln(format_args!("{}", red(options.color, &msg)));
+                return (i, Err(e), out);
+            }
+            thread::sleep(wait);
+        }
+        let result = action.execute(disp, i, options, &mut out);
+        if let Err(e) = &result {
+            let msg = render_error(e, Some(&display_name(i, disp)));
+            out.eprintln(format_args!("{}", red(options.color, &msg)));
+        }
+        (i, result, out)
+    };
+    let run = |i: usize, disp: &mut Monitor, action: Action| {
+        progress::begin(i);
+        let result = execute(i, disp, action);
+        progress::end(i);
+        result
+    };
+
+    let total = jobs.len();
+    progress::start(total);
+    let results: Vec<_> = if fail_fast {
+        // displays are done one at a time so that nothing is changed after the first failure
+        let mut results = Vec::new();
+        for (i, disp, action) in jobs {
+            let result = run(i, disp, action);
+            let failed = result.1.is_err();
+            results.push(result);
+            if failed || signal::interrupted() {
+                break;
+            }
+        }
+        results
+    } else {
+        in_parallel(jobs, max_concurrency, |(i, disp, action)| {
+            run(i, disp, action)
+        })
+    };
+
+    progress::finish();
+    let attempted = results.len();
+    let first_value = results
+        .first()
+        .and_then(|(_, result, _)| *result.as_ref().ok()?);
+    let changed: Vec<_> = results
+        .iter()
+        .filter_map(|(i, result, _)| Some((*i, (*result.as_ref().ok()?)?)))
+        .collect();
+    let mut failed = Vec::new();
+    let mut rows = Vec::new();
+    for (i, result, out) in results {
+        let after = result.as_ref().ok().copied().flatten();
+        let status = match (&result, out.before()) {
+            (Err(_), _) => "failed",
+            (Ok(_), before) if before == after => "unchanged",
+            _ if options.dry_run => "dry run",
+            _ => "changed",
+        };
+        let model = displays
+            .iter()
+            .find(|(n, _)| *n == i)
+            .and_then(|(_, disp)| disp.info.model_name.clone());
+        rows.push(ChangeRow {
+            display: i,
+            model,
+            before: out.before(),
+            after,
+            status,
+        });
+        out.flush();
+        if let Err(Error::UnsupportedFeature(feature)) = &result
+            && cache != CacheMode::Disabled
+            && let Some((_, disp)) = displays.iter().find(|(n, _)| *n == i)
+            && let Err(e) = cache::mark_unsupported(&disp.info, feature.code())
+        {
+            let msg = format!("Failed to write display cache: {e}");
+            error(options.color, &msg);
+        }
+        if result.is_err() {
+            failed.push(i);
+        }
+    }
+
+    // profiles only ever change brightness
+    let feature = match action {
+        Action::Change(feature, _) => Some(feature),
+        _ if profile.is_some() => Some(Feature::Luminance),
+        _ => None,
+    };
+    // with --json the table is the output, so it's printed for a single display too
+    if (total > 1 || options.json) && feature.is_some() {
+        let mut out = Output::new(options);
+        print_change_table(&rows, options, &mut out);
+        out.flush();
+    }
+
+    if total > 1 && !options.json {
+        let verb = match action {
+            _ if action.is_read_only() => "read",
+            _ if options.dry_run => "checked",
+            _ => "updated",
+        };
+        let mut summary = format!("{}/{total} displays {verb}", attempted - failed.len());
+        if !failed.is_empty() {
+            let names: Vec<_> = displays
+                .iter()
+                .filter(|(i, _)| failed.contains(i))
+                .map(|(i, disp)| display_name(*i, disp))
+                .collect();
+            summary.push_str(&format!(", {} failed: {}", failed.len(), names.join(", ")));
+        }
+        if attempted < total {
+            summary.push_str(&format!(", {} skipped", total - attempted));
+        }
+
+        if failed.is_empty() {
+            log::info(format_args!("{summary}"));
+        } else {
+            error(options.color, &summary);
+        }
+    }
+
+    // missing displays don't fail the run, which only depends on the displays which are present
+    if let Some((name, _)) = &profile
+        && missing > 0
+        && !options.json
+    {
+        let verb = if options.dry_run {
+            "checked"
+        } else {
+            "applied"
+        };
+        let applied = attempted - failed.len();
+        log::info(format_args!(
+            "Loaded {name}: {applied} {verb}, {missing} not connected"
+        ));
+    }
+
+    if notify
+        && !options.dry_run
+        && let Some(feature) = feature
+    {
+        notify_change(feature, &changed, options);
+    }
+
+    if let (Some(_), Action::Change(_, BrightnessChange::Absolute(target))) = (equalize, action)
+        && total > 0
+        && failed.is_empty()
+        && !options.json
+    {
+        log::info(format_args!("Equalized brightness at {target}%"));
+    }
+
+    if exit_with_value && matches!(action, Action::Get(features) if features.is_brightness()) {
+        // values are capped at 254, which only raw values can reach, so that 255 always means
+        // the read failed
+        return match first_value {
+            Some(percent) if failed.is_empty() => ExitCode::from(percent.min(254) as u8),
+            _ => failure(),
+        };
+    }
+
+    // cancelling the countdown isn't a failure, as the action itself was carried out
+    let interrupted = signal::interrupted();
+    if let Some(wait) = off_after
+        && !interrupted
+        && !switch_off_after(&mut displays, wait, per_display, options)
+    {
+        return failure();
+    }
+
+    if interrupted {
+        failure()
+    } else if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else if failed.len() < attempted {
+        partial_failure()
+    } else {
+        failure()
+    }
 }
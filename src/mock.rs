@@ -0,0 +1,63 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ddc_hi::DisplayInfo;
+
+use crate::{Backend, BrightnessBackend, DdcError, Error, Feature, Monitor};
+
+/// A display which keeps its features in memory, shared between clones so that tests can
+/// check what was written after handing one to a [`Monitor`].
+#[derive(Clone, Default)]
+pub struct MockBackend {
+    // the raw value and maximum of each supported feature, by code
+    features: Arc<Mutex<HashMap<u8, (u16, u16)>>>,
+}
+
+impl MockBackend {
+    pub fn with_feature(self, code: u8, value: u16, maximum: u16) -> Self {
+        self.lock().insert(code, (value, maximum));
+        self
+    }
+
+    /// The raw value of the feature with `code`, if it is supported.
+    pub fn value(&self, code: u8) -> Option<u16> {
+        self.lock().get(&code).map(|&(value, _)| value)
+    }
+
+    /// A display with no EDID using this backend, so that nothing is recorded for `--undo`.
+    pub fn monitor(&self) -> Monitor {
+        Monitor {
+            info: DisplayInfo::new(ddc_hi::Backend::I2cDevice, "mock".to_string()),
+            backend: Backend::Custom(Box::new(self.clone())),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u8, (u16, u16)>> {
+        self.features.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl BrightnessBackend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn read(&mut self, feature: Feature) -> Result<(u16, u16), Error> {
+        self.lock()
+            .get(&feature.code())
+            .copied()
+            .ok_or(Error::UnsupportedFeature(feature))
+    }
+
+    fn write(&mut self, code: u8, value: u16) -> Result<(), DdcError> {
+        match self.lock().get_mut(&code) {
+            Some((current, _)) => {
+                *current = value;
+                Ok(())
+            }
+            None => Err(DdcError::msg(format!("Unsupported VCP code 0x{code:02X}"))),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::process::{Command, Stdio};
+
+use crate::log;
+
+/// Shows a desktop notification with `notify-send`, replacing the last one this program showed
+/// so that repeated changes don't pile up. With `percent` notification daemons which support it
+/// show a progress bar.
+///
+/// Nothing is shown if there is no notification daemon or `notify-send` isn't installed.
+pub fn send(summary: &str, body: &str, percent: Option<u16>) {
+    let mut command = Command::new("notify-send");
+    command.args([
+        "--app-name=ddc-brightness-ctl",
+        "--hint=string:x-canonical-private-synchronous:ddc-brightness-ctl",
+    ]);
+    if let Some(percent) = percent {
+        command.arg(format!("--hint=int:value:{percent}"));
+    }
+    command
+        .args([summary, body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // the change has already been made, so failing to show it isn't an error
+    match command.status() {
+        Ok(status) if !status.success() => {
+            log::debug(format_args!("notify-send failed with {status}"));
+        }
+        Err(e) => log::debug(format_args!("Failed to run notify-send: {e}")),
+        Ok(_) => {}
+    }
+}
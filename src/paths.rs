@@ -0,0 +1,35 @@
+use std::{env, path::PathBuf};
+
+const APP_DIR: &str = env!("CARGO_PKG_NAME");
+
+// resolves an XDG base directory, falling back to the given path under $HOME when unset, or on
+// Windows to the known folder in `windows_var` as there usually is no $HOME
+fn xdg_dir(var: &str, home_fallback: &str, windows_var: &str) -> Option<PathBuf> {
+    let base = match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ if cfg!(windows) => PathBuf::from(env::var_os(windows_var)?),
+        _ => PathBuf::from(env::var_os("HOME")?).join(home_fallback),
+    };
+
+    Some(base.join(APP_DIR))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config", "APPDATA")
+}
+
+// unlike the other directories there is no fallback, as nothing else has the same lifetime
+pub fn runtime_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join(APP_DIR)),
+        _ => None,
+    }
+}
+
+pub fn state_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state", "LOCALAPPDATA")
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME", ".cache", "LOCALAPPDATA")
+}
@@ -0,0 +1,178 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+const PROFILE_DIR: &str = "profiles";
+
+/// The settings saved for one display, identified by its serial number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplaySettings {
+    pub serial: u32,
+    /// Only kept to make the file readable, displays are matched by serial.
+    pub model: Option<String>,
+    pub brightness: Option<u16>,
+}
+
+/// A named set of display settings, stored as TOML under
+/// `$XDG_CONFIG_HOME/ddc-brightness-ctl/profiles/NAME.toml`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub displays: Vec<DisplaySettings>,
+}
+
+impl Profile {
+    pub fn get(&self, serial: u32) -> Option<&DisplaySettings> {
+        self.displays
+            .iter()
+            .find(|display| display.serial == serial)
+    }
+
+    /// Replaces the settings saved for the display, or adds them if it has none.
+    pub fn insert(&mut self, settings: DisplaySettings) {
+        match self
+            .displays
+            .iter_mut()
+            .find(|display| display.serial == settings.serial)
+        {
+            Some(display) => *display = settings,
+            None => self.displays.push(settings),
+        }
+    }
+
+    // only the small subset of TOML written by `format` is understood: one table per display
+    // named display.SERIAL, containing integer and basic string values
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut profile = Self::default();
+        for (line_no, line) in contents.lines().enumerate() {
+            let line_no = line_no + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let serial = header
+                    .trim()
+                    .strip_prefix("display.")
+                    .and_then(|serial| u32::from_str_radix(serial, 16).ok())
+                    .ok_or_else(|| format!("line {line_no}: expected [display.SERIAL]"))?;
+                profile.displays.push(DisplaySettings {
+                    serial,
+                    model: None,
+                    brightness: None,
+                });
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {line_no}: expected key = value"))?;
+            let display = profile
+                .displays
+                .last_mut()
+                .ok_or_else(|| format!("line {line_no}: value outside of a display table"))?;
+            let value = value.trim();
+            match key.trim() {
+                "model" => display.model = Some(parse_string(value, line_no)?),
+                "brightness" => {
+                    let brightness = value
+                        .parse()
+                        .map_err(|e| format!("line {line_no}: invalid brightness: {e}"))?;
+                    display.brightness = Some(brightness);
+                }
+                // unknown keys are left alone so newer profiles still load
+                _ => {}
+            }
+        }
+
+        Ok(profile)
+    }
+
+    fn format(&self, comment: &str) -> String {
+        let mut contents = format!("# {comment}\n");
+        for display in &self.displays {
+            contents.push_str(&format!("\n[display.{:08X}]\n", display.serial));
+            if let Some(model) = &display.model {
+                contents.push_str(&format!("model = {}\n", quote(model)));
+            }
+            if let Some(brightness) = display.brightness {
+                contents.push_str(&format!("brightness = {brightness}\n"));
+            }
+        }
+
+        contents
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub(crate) fn parse_string(value: &str, line_no: usize) -> Result<String, String> {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("line {line_no}: expected a quoted string"))?;
+
+    let mut s = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        s.push(match c {
+            '\\' => match chars.next() {
+                Some('\\') => '\\',
+                Some('"') => '"',
+                _ => return Err(format!("line {line_no}: unsupported escape sequence")),
+            },
+            c => c,
+        });
+    }
+
+    Ok(s)
+}
+
+/// The file the profile `name` is stored in.
+pub fn path(name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid profile name '{name}'"),
+        ));
+    }
+
+    let dir = paths::config_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "neither $XDG_CONFIG_HOME nor $HOME is set",
+        )
+    })?;
+
+    Ok(dir.join(PROFILE_DIR).join(format!("{name}.toml")))
+}
+
+pub fn load(name: &str) -> io::Result<Profile> {
+    load_from(&path(name)?)
+}
+
+pub fn save(name: &str, profile: &Profile) -> io::Result<()> {
+    let comment = format!("ddc-brightness-ctl profile {}", quote(name));
+    save_to(&path(name)?, profile, &comment)
+}
+
+// profiles are also used to record state, which lives outside the profile directory
+pub(crate) fn load_from(path: &Path) -> io::Result<Profile> {
+    let contents = fs::read_to_string(path)?;
+    Profile::parse(&contents).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+pub(crate) fn save_to(path: &Path, profile: &Profile, comment: &str) -> io::Result<()> {
+    fs::create_dir_all(path.parent().expect("profile path has a parent"))?;
+    fs::write(path, profile.format(comment))
+}
@@ -0,0 +1,129 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::log;
+
+static STATE: Mutex<State> = Mutex::new(State {
+    enabled: false,
+    total: 0,
+    done: 0,
+    active: Vec::new(),
+    drawn: false,
+});
+
+struct State {
+    enabled: bool,
+    // how many displays the sweep covers and how many of them have finished
+    total: usize,
+    done: usize,
+    // (display number, value and target of its fade) for each display being worked on
+    active: Vec<(usize, Option<(u16, u16)>)>,
+    // whether the line is on screen, so it has to be cleared before anything else is printed
+    drawn: bool,
+}
+
+impl State {
+    fn clear(&mut self) {
+        if self.drawn {
+            eprint!("\r\x1b[K");
+            self.drawn = false;
+        }
+    }
+
+    // e.g. `[1/3] display 0 34/80, display 2`, nothing is drawn for a single display unless it
+    // is fading as it would only flash up
+    fn draw(&mut self) {
+        self.clear();
+        let fading = self.active.iter().any(|(_, fade)| fade.is_some());
+        if !self.enabled || self.active.is_empty() || (self.total < 2 && !fading) {
+            return;
+        }
+
+        let mut line = String::new();
+        if self.total > 1 {
+            line.push_str(&format!(
+                "[{}/{}] ",
+                (self.done + 1).min(self.total),
+                self.total
+            ));
+        }
+        let displays: Vec<_> = self
+            .active
+            .iter()
+            .map(|(display_no, fade)| match fade {
+                Some((value, target)) => format!("display {display_no} {value}/{target}"),
+                None => format!("display {display_no}"),
+            })
+            .collect();
+        line.push_str(&displays.join(", "));
+        eprint!("{line}");
+        let _ = io::stderr().flush();
+        self.drawn = true;
+    }
+}
+
+fn state() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts showing progress through `total` displays on stderr, as a single line rewritten in
+/// place. Nothing is shown with `--quiet` or when stderr isn't a terminal.
+pub fn start(total: usize) {
+    let mut state = state();
+    state.enabled = log::enabled(log::Level::Info) && io::stderr().is_terminal();
+    state.total = total;
+    state.done = 0;
+    state.active.clear();
+}
+
+/// Clears the line for good, so that the output of each display and the summary can follow.
+pub fn finish() {
+    let mut state = state();
+    state.clear();
+    state.enabled = false;
+}
+
+/// Marks the display numbered `display_no` as being worked on.
+pub fn begin(display_no: usize) {
+    let mut state = state();
+    if state.enabled {
+        state.active.push((display_no, None));
+        state.draw();
+    }
+}
+
+/// Marks the display numbered `display_no` as done, whether or not it succeeded.
+pub fn end(display_no: usize) {
+    let mut state = state();
+    if state.enabled {
+        state.active.retain(|(n, _)| *n != display_no);
+        state.done += 1;
+        state.draw();
+    }
+}
+
+/// Shows that a fade of the display numbered `display_no` has reached `value` of `target`.
+pub fn fade(display_no: usize, value: u16, target: u16) {
+    let mut state = state();
+    if !state.enabled {
+        return;
+    }
+    if let Some((_, fade)) = state.active.iter_mut().find(|(n, _)| *n == display_no) {
+        *fade = Some((value, target));
+        state.draw();
+    }
+}
+
+/// Runs `print` with the line cleared, then puts it back, so that messages printed while it is
+/// shown aren't written over it.
+pub(crate) fn around(print: impl FnOnce()) {
+    let mut state = state();
+    let drawn = state.drawn;
+    state.clear();
+    print();
+    if drawn {
+        state.draw();
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    f64::consts::TAU,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const MINUTES_PER_DAY: f64 = 24.0 * 60.0;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// the sun's apparent radius plus atmospheric refraction, as the zenith angle of sunrise
+const SUNRISE_ZENITH: f64 = 90.833;
+
+/// Whether it is day or night, `--auto` loads the profile of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Night,
+}
+
+impl Period {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Night => "night",
+        }
+    }
+}
+
+/// How `--auto` decides whether it is day or night.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// Fixed sunrise and sunset times, in minutes after local midnight.
+    Times { sunrise: u16, sunset: u16 },
+    /// Sunrise and sunset calculated for a location, in degrees north and east.
+    Location { latitude: f64, longitude: f64 },
+}
+
+impl Schedule {
+    pub fn period(self, now: SystemTime) -> Period {
+        let secs = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        let (minute, sunrise, sunset) = match self {
+            Self::Times { sunrise, sunset } => {
+                (local_minute(secs), f64::from(sunrise), f64::from(sunset))
+            }
+            Self::Location {
+                latitude,
+                longitude,
+            } => {
+                let days = secs / SECONDS_PER_DAY;
+                let minute = (secs % SECONDS_PER_DAY) as f64 / 60.0;
+                match sun_times(days, latitude, longitude) {
+                    Ok((sunrise, sunset)) => (minute, sunrise, sunset),
+                    Err(period) => return period,
+                }
+            }
+        };
+
+        let sunrise = sunrise.rem_euclid(MINUTES_PER_DAY);
+        let sunset = sunset.rem_euclid(MINUTES_PER_DAY);
+        let day = if sunrise <= sunset {
+            sunrise <= minute && minute < sunset
+        } else {
+            // in UTC the day can span midnight
+            minute >= sunrise || minute < sunset
+        };
+
+        if day { Period::Day } else { Period::Night }
+    }
+}
+
+/// Parses a time of day in the form `HH:MM`, as minutes after midnight.
+pub fn parse_time(s: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid time '{s}', expected HH:MM");
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    if minutes.len() != 2 {
+        return Err(invalid());
+    }
+    match (hours.parse::<u16>(), minutes.parse::<u16>()) {
+        (Ok(hours @ 0..=23), Ok(minutes @ 0..=59)) => Ok(hours * 60 + minutes),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a location in the form `LAT,LON`, in degrees north and east.
+pub fn parse_location(s: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("invalid location '{s}', expected LAT,LON in degrees");
+    let (latitude, longitude) = s.split_once(',').ok_or_else(invalid)?;
+    match (latitude.trim().parse(), longitude.trim().parse()) {
+        (Ok(latitude @ -90.0..=90.0), Ok(longitude @ -180.0..=180.0)) => Ok((latitude, longitude)),
+        _ => Err(invalid()),
+    }
+}
+
+// sunrise and sunset in minutes after UTC midnight, using NOAA's approximation of the sun's
+// position, or the period it stays in all day near the poles
+fn sun_times(days: u64, latitude: f64, longitude: f64) -> Result<(f64, f64), Period> {
+    // the fraction of the year is off by up to a day depending on leap years, which moves
+    // sunrise by well under a minute
+    let year = TAU * (days as f64 / 365.2425).fract();
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * year.cos()
+            - 0.032077 * year.sin()
+            - 0.014615 * (2.0 * year).cos()
+            - 0.040849 * (2.0 * year).sin());
+    let declination = 0.006918 - 0.399912 * year.cos() + 0.070257 * year.sin()
+        - 0.006758 * (2.0 * year).cos()
+        + 0.000907 * (2.0 * year).sin()
+        - 0.002697 * (3.0 * year).cos()
+        + 0.00148 * (3.0 * year).sin();
+
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = SUNRISE_ZENITH.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    if cos_hour_angle < -1.0 {
+        return Err(Period::Day);
+    } else if cos_hour_angle > 1.0 {
+        return Err(Period::Night);
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    let noon = 720.0 - 4.0 * longitude - equation_of_time;
+    Ok((noon - 4.0 * hour_angle, noon + 4.0 * hour_angle))
+}
+
+// the minute of the day in the local timezone
+#[cfg(unix)]
+fn local_minute(secs: u64) -> f64 {
+    let time = secs as libc::time_t;
+    // SAFETY: an all zero tm is valid, and localtime_r only writes to the tm given to it
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return (secs % SECONDS_PER_DAY) as f64 / 60.0;
+    }
+
+    f64::from(tm.tm_hour * 60 + tm.tm_min)
+}
+
+// without a portable way to find the timezone, times are taken as UTC
+#[cfg(not(unix))]
+fn local_minute(secs: u64) -> f64 {
+    (secs % SECONDS_PER_DAY) as f64 / 60.0
+}
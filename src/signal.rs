@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl-C so that long running modes can stop cleanly, check [`interrupted`] to see
+/// whether it has been pressed.
+///
+/// On platforms other than unix this does nothing and Ctrl-C exits immediately.
+pub fn catch_interrupt() {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_interrupt(_signal: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+
+        let handler = on_interrupt as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        }
+    }
+}
+
+/// Whether Ctrl-C has been pressed since [`catch_interrupt`] was called.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
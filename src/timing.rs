@@ -0,0 +1,50 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// (display number, what was done, how long it took), in the order they finished
+static TIMINGS: Mutex<Vec<(Option<usize>, String, Duration)>> = Mutex::new(Vec::new());
+
+/// Starts recording how long operations take, for `--timing`.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Runs `op`, recording how long it took as `what`, done to the display numbered `display`.
+///
+/// `what` is only called when timing is enabled.
+pub fn time<T>(display: Option<usize>, what: impl FnOnce() -> String, op: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return op();
+    }
+
+    let start = Instant::now();
+    let result = op();
+    let elapsed = start.elapsed();
+    TIMINGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((display, what(), elapsed));
+
+    result
+}
+
+/// Takes everything recorded so far, grouped by display with operations which weren't done to
+/// a display first.
+pub fn take() -> Vec<(String, Duration)> {
+    let mut timings = std::mem::take(&mut *TIMINGS.lock().unwrap_or_else(|e| e.into_inner()));
+    timings.sort_by_key(|(display, _, _)| *display);
+    timings
+        .into_iter()
+        .map(|(_, what, elapsed)| (what, elapsed))
+        .collect()
+}
@@ -0,0 +1,44 @@
+use std::{io, path::PathBuf, sync::Mutex};
+
+use crate::{
+    paths,
+    profile::{self, DisplaySettings, Profile},
+};
+
+const FILE_NAME: &str = "last.toml";
+const COMMENT: &str = "ddc-brightness-ctl brightness before the last change";
+
+// displays are changed from several threads, which would otherwise lose each other's updates
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub fn path() -> Option<PathBuf> {
+    paths::state_dir().map(|dir| dir.join(FILE_NAME))
+}
+
+/// The brightness each display had before it was last changed, in the same form as a profile.
+pub fn load() -> io::Result<Profile> {
+    let Some(path) = path() else {
+        return Ok(Profile::default());
+    };
+
+    match profile::load_from(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Profile::default()),
+        result => result,
+    }
+}
+
+// records the brightness a display had before being changed, so that it can be restored
+pub(crate) fn record(serial: u32, model: Option<String>, brightness: u16) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Ok(());
+    };
+
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut state = load()?;
+    state.insert(DisplaySettings {
+        serial,
+        model,
+        brightness: Some(brightness),
+    });
+    profile::save_to(&path, &state, COMMENT)
+}